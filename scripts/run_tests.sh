@@ -2,13 +2,16 @@
 
 TEST_SUITE=${1:-all}
 
+# marks the test processes, for `tools reset`
+export HCBS_TEST_SUITE=1
+
 print_help() {
     echo "Usage: $0 [<test_suite>] | $0 [help|-h|--help]"
     echo "Available Test Suites:"
     echo "-   all (or no argument) : run all test suites"
     echo "-            constraints : run constraints tests"
-    echo "-                   time : run time tests (~100s runtime)"
-    echo "-             regression : run regression tests (~17m runtime)"
+    echo "-                   time : run time tests (~360s runtime)"
+    echo "-             regression : run regression tests (~19m runtime)"
     echo ""
     echo "-                   full : run all test suites + excluded ones"
     echo "- ---------------------- : excluded tests from the all command -------------"
@@ -30,6 +33,8 @@ setup() {
 constraints() {
     echo "* Constraints Tests *"
     ./test_suite_v2/constraints_cgroup_setup
+    ./test_suite_v2/constraints_policy_matrix
+    ./test_suite_v2/constraints_fuzzer
 }
 
 time_tests() {
@@ -55,6 +60,27 @@ time_tests() {
         ./test_suite_v2/time many -n 5 -r 10 -p 100 --cpu-set 0-7 -t 10
     BATCH_TEST_CUSTOM_NAME="five-tasks-all-cpus" \
         ./test_suite_v2/time many -n 5 -r 5 -p 100 -t 10
+
+    # every cpu gets its own runtime
+    BATCH_TEST_CUSTOM_NAME="per-cpu-four-cpus" \
+        ./test_suite_v2/time many --per-cpu -r 20 -p 100 --cpu-set 0-3 -t 10
+    BATCH_TEST_CUSTOM_NAME="per-cpu-all-cpus" \
+        ./test_suite_v2/time many --per-cpu -r 40 -p 100 -t 10
+
+    # budget enforcement with and without the high resolution DL tick
+    ./test_suite_v2/time many -r 40 -p 100 --cpu-set 0 -t 10 --feature-matrix HRTICK_DL
+    ./test_suite_v2/time many -n 5 -r 20 -p 100 --cpu-set 0-3 -t 10 --feature-matrix HRTICK_DL
+
+    # bandwidth distribution in cgroup hierarchies
+    BATCH_TEST_CUSTOM_NAME="nested-two-levels" \
+        ./test_suite_v2/time nested -t 10
+    BATCH_TEST_CUSTOM_NAME="nested-three-levels" \
+        ./test_suite_v2/time nested -g g0:60/100 -g g0/g1:40/100 -g g0/g1/g2:20/100 -g g0/g1/g3:15/100 -g g0/g4:10/100 -t 10
+
+    # round robin fairness with different timeslices, the default one needs
+    # longer windows
+    ./test_suite_v2/time rr -t 20
+    ./test_suite_v2/time rr -R 60/100 -s 100 -t 60 -k 6
 }
 
 regression() {
@@ -66,6 +92,12 @@ regression() {
     TESTBINDIR=test_suite_v2 ./test_suite_v2/regression deadline -r 10 -p 100 -t 60
     TESTBINDIR=test_suite_v2 ./test_suite_v2/regression deadline -r 20 -p 100 -t 60
     TESTBINDIR=test_suite_v2 ./test_suite_v2/regression deadline -r 30 -p 100 -t 60
+    ./test_suite_v2/regression pi-mutex -r 10 -p 100
+    ./test_suite_v2/regression pi-mutex -r 10 -p 100 -s 25 --high-cgroup g1
+    ./test_suite_v2/regression fork -r 10 -p 100 -t 30
+    ./test_suite_v2/regression fork -r 50 -p 100 -t 30 --rate 5000
+    ./test_suite_v2/regression priority-order -r 30 -p 100 -t 10
+    ./test_suite_v2/regression priority-order -r 10 -p 100 -n 5 --work 1 -t 10
     BATCH_TEST_CUSTOM_NAME="migration-regression" \
         ./test_suite_v2/stress task-migration -r 1 -p 100 -P 0.1 -t 300
     BATCH_TEST_CUSTOM_NAME="affinity-regression" \
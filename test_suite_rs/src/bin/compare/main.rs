@@ -0,0 +1,198 @@
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::tests::taskset::{read_output_dir, TasksetOutputSummary};
+
+/// Compare the results of two test runs
+///
+/// This command compares a baseline result set against a new one, e.g. the
+/// results of two revisions of the HCBS patchset. A result set is made of the
+/// JSON report of the batch tests (written to the file given in the
+/// BATCH_TEST_JSON environment variable) and/or the output directory of the
+/// taskset tests.
+///
+/// The command reports newly failing and newly passing tests, the change of
+/// the measured bandwidths of the time and regression tests (worse when
+/// further from the expected bandwidth), and the change of
+/// the deadline overrun ratio of each taskset. It fails if any regression
+/// exceeding the given thresholds is found.
+#[derive(clap::Parser, Debug)]
+#[command(verbatim_doc_comment)]
+pub struct MyArgs {
+    /// baseline JSON report
+    #[arg(long = "old-results", value_name = "path", requires = "new_results")]
+    pub old_results: Option<String>,
+
+    /// new JSON report
+    #[arg(long = "new-results", value_name = "path", requires = "old_results")]
+    pub new_results: Option<String>,
+
+    /// baseline taskset output directory
+    #[arg(long = "old-tasksets", value_name = "path", requires = "new_tasksets")]
+    pub old_tasksets: Option<String>,
+
+    /// new taskset output directory
+    #[arg(long = "new-tasksets", value_name = "path", requires = "old_tasksets")]
+    pub new_tasksets: Option<String>,
+
+    /// worsening of a measurement (in CPU units for bandwidths) over which it
+    /// is a regression: moving further away from its expected value, or
+    /// growing if it has none
+    #[arg(short = 'b', long = "bw-threshold", value_name = "f64", default_value = "0.01")]
+    pub bw_threshold: f64,
+
+    /// increase of the overrun ratio over which a taskset is a regression
+    #[arg(short = 'o', long = "overrun-threshold", value_name = "f64", default_value = "0.0")]
+    pub overrun_threshold: f64,
+}
+
+fn compare_results(old_file: &str, new_file: &str, args: &MyArgs) -> Result<u64, Box<dyn std::error::Error>> {
+    let old = read_test_records(old_file)?;
+    let new = read_test_records(new_file)?;
+
+    println!("[compare] Test results: {old_file} -> {new_file}");
+
+    let mut regressions = 0u64;
+    let mut newly_failing = Vec::new();
+    let mut newly_passing = Vec::new();
+    let mut bw_changes = Vec::new();
+    let mut missing = Vec::new();
+    let mut added = Vec::new();
+
+    for (key, new_record) in new.iter() {
+        let test_name = format!("[{}] {}", key.0, key.1);
+
+        let Some(old_record) = old.get(key) else {
+            added.push(format!("{test_name} ({})", new_record.outcome));
+            continue;
+        };
+
+        let old_failed = old_record.outcome == TestOutcome::Failure;
+        let new_failed = new_record.outcome == TestOutcome::Failure;
+        if !old_failed && new_failed {
+            regressions += 1;
+            newly_failing.push(format!("{test_name}: {}",
+                new_record.details.as_deref().unwrap_or("no details")));
+        } else if old_failed && new_record.outcome == TestOutcome::Success {
+            newly_passing.push(test_name.clone());
+        }
+
+        for (metric, new_value) in new_record.metrics.iter() {
+            let Some(old_value) = old_record.metrics.get(metric) else { continue; };
+
+            // a measurement gets worse when it moves away from its expected
+            // value, or when it grows if it has none (e.g. a latency)
+            let delta = new_value - old_value;
+            let worsening = match new_record.expected.get(metric).or(old_record.expected.get(metric)) {
+                Some(expected) => (new_value - expected).abs() - (old_value - expected).abs(),
+                None => delta,
+            };
+
+            let regression = worsening > args.bw_threshold;
+            if regression {
+                regressions += 1;
+            }
+
+            bw_changes.push(format!("{test_name} / {metric}: {old_value:.5} -> {new_value:.5} ({delta:+.5}){}",
+                if regression { " REGRESSION" } else { "" }));
+        }
+    }
+
+    for key in old.keys() {
+        if !new.contains_key(key) {
+            missing.push(format!("[{}] {}", key.0, key.1));
+        }
+    }
+
+    print_section("Newly failing", "-", &newly_failing);
+    print_section("Newly passing", "+", &newly_passing);
+    print_section("Bandwidth changes", "*", &bw_changes);
+    print_section("Not run anymore", "?", &missing);
+    print_section("New tests", "?", &added);
+
+    Ok(regressions)
+}
+
+fn compare_tasksets(old_dir: &str, new_dir: &str, args: &MyArgs) -> Result<u64, Box<dyn std::error::Error>> {
+    let old = read_output_dir(old_dir)?;
+    let new = read_output_dir(new_dir)?;
+
+    println!("[compare] Tasksets: {old_dir} -> {new_dir}");
+
+    let find_old = |new: &TasksetOutputSummary| {
+        old.iter().find(|old| old.taskset == new.taskset && old.config == new.config)
+    };
+
+    let mut regressions = 0u64;
+    let mut changes = Vec::new();
+    let mut added = Vec::new();
+    for new_run in new.iter() {
+        let run_name = format!("{} on {}", new_run.taskset, new_run.config);
+
+        let Some(old_run) = find_old(new_run) else {
+            added.push(format!("{run_name} ({:.2} % overruns)", new_run.overruns_ratio * 100f64));
+            continue;
+        };
+
+        let delta = new_run.overruns_ratio - old_run.overruns_ratio;
+        if delta == 0f64 {
+            continue;
+        }
+
+        let regression = delta > args.overrun_threshold;
+        if regression {
+            regressions += 1;
+        }
+
+        changes.push(format!("{run_name}: overruns {:.2} % -> {:.2} % ({:+.2} %), worst overrun {:.3} -> {:.3}{}",
+            old_run.overruns_ratio * 100f64, new_run.overruns_ratio * 100f64, delta * 100f64,
+            old_run.worst_overrun, new_run.worst_overrun,
+            if regression { " REGRESSION" } else { "" }));
+    }
+
+    let missing: Vec<_> = old.iter()
+        .filter(|old_run| !new.iter().any(|new_run|
+            new_run.taskset == old_run.taskset && new_run.config == old_run.config))
+        .map(|old_run| format!("{} on {}", old_run.taskset, old_run.config))
+        .collect();
+
+    print_section("Overrun ratio changes", "*", &changes);
+    print_section("Not run anymore", "?", &missing);
+    print_section("New tasksets", "?", &added);
+
+    Ok(regressions)
+}
+
+fn print_section(title: &str, marker: &str, lines: &[String]) {
+    if lines.is_empty() {
+        return;
+    }
+
+    println!("  {title}:");
+    for line in lines {
+        println!("    {marker} {line}");
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = <MyArgs as clap::Parser>::parse();
+
+    if args.old_results.is_none() && args.old_tasksets.is_none() {
+        Err("Nothing to compare: provide test results and/or taskset directories")?;
+    }
+
+    let mut regressions = 0u64;
+    if let (Some(old), Some(new)) = (&args.old_results, &args.new_results) {
+        regressions += compare_results(old, new, &args)?;
+    }
+
+    if let (Some(old), Some(new)) = (&args.old_tasksets, &args.new_tasksets) {
+        regressions += compare_tasksets(old, new, &args)?;
+    }
+
+    println!("[compare] Outcome: {regressions} regressions");
+
+    if regressions > 0 {
+        Err(format!("Found {regressions} regressions"))?;
+    }
+
+    Ok(())
+}
@@ -58,6 +58,228 @@ fn set_runtime_zero_to_active(cgroup_name: &str) -> Result<(), Box<dyn std::erro
     }
 }
 
+fn child_exceeds_parent(parent: &str, child: &str, runtime_us: u64, period_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    cgroup_setup(parent, 50_000, 100_000)?;
+
+    let failure = cgroup_setup(child, runtime_us, period_us);
+
+    delete_cgroup(child)?;
+    delete_cgroup(parent)?;
+
+    if failure.is_ok() {
+        Err(format!("Child cgroup '{child}' setup with {runtime_us}/{period_us} exceeding the parent's bandwidth did not fail"))?
+    } else {
+        Ok(())
+    }
+}
+
+fn siblings_exceed_parent(parent: &str, child1: &str, child2: &str) -> Result<(), Box<dyn std::error::Error>> {
+    cgroup_setup(parent, 50_000, 100_000)?;
+    cgroup_setup(child1, 30_000, 100_000)?;
+
+    let failure = cgroup_setup(child2, 30_000, 100_000);
+
+    delete_cgroup(child2)?;
+    delete_cgroup(child1)?;
+    delete_cgroup(parent)?;
+
+    if failure.is_ok() {
+        Err("Sibling cgroups with a total bandwidth greater than their parent's must not be allowed")?
+    } else {
+        Ok(())
+    }
+}
+
+fn parent_shrink_below_children(parent: &str, child: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::__set_cgroup_runtime_us;
+
+    cgroup_setup(parent, 50_000, 100_000)?;
+    cgroup_setup(child, 30_000, 100_000)?;
+
+    let failure = __set_cgroup_runtime_us(parent, 20_000);
+
+    delete_cgroup(child)?;
+    delete_cgroup(parent)?;
+
+    if failure.is_ok() {
+        Err("Parent cgroup's bandwidth must not shrink below the sum of its children's")?
+    } else {
+        Ok(())
+    }
+}
+
+// Runs a task in the child cgroup, then applies the given change to the
+// parent, which must be rejected.
+fn change_parent_with_active_child<F>(parent: &str, child: &str, what: &str, change: F) -> Result<(), Box<dyn std::error::Error>>
+    where F: FnOnce(&str) -> Result<(), Box<dyn std::error::Error>>
+{
+    cgroup_setup(parent, 50_000, 100_000)?;
+    cgroup_setup(child, 40_000, 100_000)?;
+    let mut yes = run_yes()?;
+    set_scheduler(yes.id(), SchedPolicy::RR(50))?;
+    migrate_task_to_cgroup(child, yes.id())?;
+
+    let failure = change(parent);
+
+    yes.kill()?;
+    migrate_task_to_cgroup(".", yes.id())?;
+    delete_cgroup(child)?;
+    delete_cgroup(parent)?;
+
+    if failure.is_ok() {
+        Err(format!("Cannot {what} of parent cgroup with active children"))?
+    } else {
+        Ok(())
+    }
+}
+
+// runtime, over the given period, giving `percent` of the bandwidth of
+// runtime_us/period_us
+fn bw_share_us(runtime_us: u64, period_us: u64, percent: u64, share_period_us: u64) -> u64 {
+    runtime_us * share_period_us * percent / (period_us * 100)
+}
+
+fn expect_unchanged(what: &str, old_value: u64, new_value: u64) -> Result<(), Box<dyn std::error::Error>> {
+    if old_value != new_value {
+        Err(format!("Rejected write changed {what} from {old_value} to {new_value}"))?;
+    }
+
+    Ok(())
+}
+
+fn cgroups_exceed_root(cgroup1: &str, cgroup2: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let runtime_us = bw_share_us(get_cgroup_runtime_us(".")?, get_cgroup_period_us(".")?, 60, 100_000);
+    cgroup_setup(cgroup1, runtime_us, 100_000)?;
+    create_cgroup(cgroup2)?;
+    __set_cgroup_period_us(cgroup2, 100_000)?;
+
+    let old_runtime_us = get_cgroup_runtime_us(cgroup2)?;
+    let failure = __set_cgroup_runtime_us(cgroup2, runtime_us);
+    let unchanged = expect_unchanged(&format!("runtime of {cgroup2}"), old_runtime_us, get_cgroup_runtime_us(cgroup2)?);
+
+    delete_cgroup(cgroup2)?;
+    delete_cgroup(cgroup1)?;
+
+    if failure.is_ok() {
+        Err("Cgroups with a total bandwidth greater than the root's must not be allowed")?
+    } else {
+        unchanged
+    }
+}
+
+fn global_runtime_below_cgroups(cgroup_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let (old_runtime_us, period_us) = (get_system_rt_runtime_us()?, get_system_rt_period_us()?);
+    cgroup_setup(cgroup_name, bw_share_us(old_runtime_us, period_us, 50, 100_000), 100_000)?;
+
+    let failure = set_system_rt_runtime_us(bw_share_us(old_runtime_us, period_us, 30, period_us));
+    let unchanged = expect_unchanged("sched_rt_runtime_us", old_runtime_us, get_system_rt_runtime_us()?);
+    if failure.is_ok() {
+        set_system_rt_runtime_us(old_runtime_us)?;
+    }
+
+    delete_cgroup(cgroup_name)?;
+
+    if failure.is_ok() {
+        Err("Global RT runtime must not be lowered below the cgroups' reservations")?
+    } else {
+        unchanged
+    }
+}
+
+fn root_runtime_below_cgroups(cgroup_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let (old_runtime_us, period_us) = (get_cgroup_runtime_us(".")?, get_cgroup_period_us(".")?);
+    cgroup_setup(cgroup_name, bw_share_us(old_runtime_us, period_us, 50, 100_000), 100_000)?;
+
+    let failure = __set_cgroup_runtime_us(".", bw_share_us(old_runtime_us, period_us, 30, period_us));
+    let unchanged = expect_unchanged("runtime of the root cgroup", old_runtime_us, get_cgroup_runtime_us(".")?);
+    if failure.is_ok() {
+        __set_cgroup_runtime_us(".", old_runtime_us)?;
+    }
+
+    delete_cgroup(cgroup_name)?;
+
+    if failure.is_ok() {
+        Err("Root cgroup's runtime must not be lowered below its children's reservations")?
+    } else {
+        unchanged
+    }
+}
+
+// One SCHED_DEADLINE task per CPU, each with the given share of the global RT
+// bandwidth. Returns the tasks and whether each was admitted.
+fn run_deadline_tasks(percent: u64) -> Result<Vec<(MyProcess, bool)>, Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let runtime_us = bw_share_us(get_system_rt_runtime_us()?, get_system_rt_period_us()?, percent, 100_000);
+    let policy = SchedPolicy::DEADLINE { runtime_ms: runtime_us / 1000, deadline_ms: 100, period_ms: 100 };
+
+    (0..CpuSet::all()?.num_cpus())
+        .map(|_| {
+            let yes = run_yes()?;
+            let admitted = set_scheduler(yes.id(), policy).is_ok();
+
+            Ok((yes, admitted))
+        })
+        .collect()
+}
+
+fn deadline_after_cgroup(cgroup_name: &str, cgroup_percent: u64, deadline_percent: u64, expect_admitted: bool) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let (global_runtime_us, global_period_us) = (get_system_rt_runtime_us()?, get_system_rt_period_us()?);
+    let runtime_us = bw_share_us(global_runtime_us, global_period_us, cgroup_percent, 100_000);
+    cgroup_setup(cgroup_name, runtime_us, 100_000)?;
+
+    let tasks = run_deadline_tasks(deadline_percent)?;
+    let num_admitted = tasks.iter().filter(|(_, admitted)| *admitted).count();
+    let num_tasks = tasks.len();
+    drop(tasks);
+
+    let unchanged = expect_unchanged(&format!("runtime of {cgroup_name}"), runtime_us, get_cgroup_runtime_us(cgroup_name)?);
+    delete_cgroup(cgroup_name)?;
+    unchanged?;
+
+    match (expect_admitted, num_admitted == num_tasks) {
+        (true, false) =>
+            Err(format!("Only {num_admitted}/{num_tasks} SCHED_DEADLINE tasks admitted within the free budget"))?,
+        (false, true) =>
+            Err("SCHED_DEADLINE tasks must not be admitted over the budget left by the cgroups")?,
+        _ => Ok(()),
+    }
+}
+
+fn cgroup_after_deadline(cgroup_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use hcbs_test_suite::cgroup::*;
+
+    let tasks = run_deadline_tasks(50)?;
+    if tasks.iter().any(|(_, admitted)| !admitted) {
+        Err("Cannot admit the SCHED_DEADLINE tasks")?;
+    }
+
+    create_cgroup(cgroup_name)?;
+    __set_cgroup_period_us(cgroup_name, 100_000)?;
+
+    let old_runtime_us = get_cgroup_runtime_us(cgroup_name)?;
+    let runtime_us = bw_share_us(get_system_rt_runtime_us()?, get_system_rt_period_us()?, 60, 100_000);
+    let failure = __set_cgroup_runtime_us(cgroup_name, runtime_us);
+    let unchanged = expect_unchanged(&format!("runtime of {cgroup_name}"), old_runtime_us, get_cgroup_runtime_us(cgroup_name)?);
+
+    drop(tasks);
+    delete_cgroup(cgroup_name)?;
+
+    if failure.is_ok() {
+        Err("Cgroup reservation must not be admitted over the budget left by SCHED_DEADLINE tasks")?
+    } else {
+        unchanged
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     mount_cgroup_fs()?;
 
@@ -91,7 +313,52 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     batch_test_header("runtime_0_while_running", test_category);
     batch_test_result(set_runtime_zero_to_active("g0"))?;
 
+    // child reservation greater than the parent's bandwidth
+    batch_test_header("child_bw_gt_parent", test_category);
+    batch_test_result(child_exceeds_parent("g0", "g0/g1", 60_000, 100_000))?;
+
+    // child reservation greater than the parent's bandwidth, with a different period
+    batch_test_header("child_bw_gt_parent_other_period", test_category);
+    batch_test_result(child_exceeds_parent("g0", "g0/g1", 30_000, 50_000))?;
+
+    // sum of the siblings' bandwidth greater than the parent's
+    batch_test_header("siblings_bw_gt_parent", test_category);
+    batch_test_result(siblings_exceed_parent("g0", "g0/g1", "g0/g2"))?;
+
+    // parent's bandwidth smaller than the sum of its children's
+    batch_test_header("parent_bw_lt_children", test_category);
+    batch_test_result(parent_shrink_below_children("g0", "g0/g1"))?;
+
     // change runtime/period of parent with child with active tasks
+    batch_test_header("parent_runtime_0_active_child", test_category);
+    batch_test_result(change_parent_with_active_child("g0", "g0/g1", "set runtime zero",
+        |parent| hcbs_test_suite::cgroup::__set_cgroup_runtime_us(parent, 0)))?;
+
+    batch_test_header("parent_period_change_active_child", test_category);
+    batch_test_result(change_parent_with_active_child("g0", "g0/g1", "increase the period (shrinking the bandwidth below the child's)",
+        |parent| hcbs_test_suite::cgroup::__set_cgroup_period_us(parent, 200_000)))?;
+
+    // sum of the cgroups' bandwidth greater than the root's
+    batch_test_header("cgroups_bw_gt_root", test_category);
+    batch_test_result(cgroups_exceed_root("g0", "g1"))?;
+
+    // global RT limit lowered below the cgroups' reservations
+    batch_test_header("global_rt_runtime_lt_cgroups", test_category);
+    batch_test_result(global_runtime_below_cgroups("g0"))?;
+
+    // root cgroup's runtime lowered below its children's reservations
+    batch_test_header("root_runtime_lt_cgroups", test_category);
+    batch_test_result(root_runtime_below_cgroups("g0"))?;
+
+    // HCBS servers and SCHED_DEADLINE tasks share the same admission budget
+    batch_test_header("deadline_and_cgroup_within_budget", test_category);
+    batch_test_result(deadline_after_cgroup("g0", 40, 40, true))?;
+
+    batch_test_header("deadline_over_cgroup_budget", test_category);
+    batch_test_result(deadline_after_cgroup("g0", 80, 30, false))?;
+
+    batch_test_header("cgroup_over_deadline_budget", test_category);
+    batch_test_result(cgroup_after_deadline("g0"))?;
 
     Ok(())
 }
\ No newline at end of file
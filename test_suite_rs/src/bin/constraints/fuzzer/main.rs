@@ -0,0 +1,291 @@
+#![feature(iterator_try_collect)]
+
+use hcbs_test_suite::*;
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::cgroup::*;
+use rand::*;
+
+mod model;
+
+use model::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// number of random sequences to run
+    #[arg(short = 'n', long = "num-sequences", value_name = "u64", default_value = "50")]
+    pub num_sequences: u64,
+
+    /// number of operations per sequence
+    #[arg(short = 'l', long = "length", value_name = "u64", default_value = "30")]
+    pub length: u64,
+
+    /// number of tasks to move around
+    #[arg(short = 'T', long = "num-tasks", value_name = "u64", default_value = "2")]
+    pub num_tasks: usize,
+
+    /// RNG's seed
+    #[arg(long = "seed", value_name = "u64", default_value = "42")]
+    pub seed: u64,
+
+    /// report the first divergent sequence as is, without shrinking it
+    #[arg(long = "no-shrink")]
+    pub no_shrink: bool,
+}
+
+const RUNTIMES_US: [u64; 6] = [0, 10_000, 20_000, 30_000, 50_000, 80_000];
+const PERIODS_US: [u64; 3] = [50_000, 100_000, 200_000];
+const POLICIES: [Policy; 3] = [Policy::Other, Policy::Fifo, Policy::Deadline];
+
+fn random_op<R: Rng>(rng: &mut R, num_tasks: usize, online: &CpuSet) -> Result<Op, Box<dyn std::error::Error>> {
+    let cgroup = rng.random_range(0..CGROUPS.len());
+    let task = rng.random_range(0..num_tasks);
+
+    let op = match rng.random_range(0..=10) {
+        0..=1 => Op::Create { cgroup },
+        2 => Op::Delete { cgroup },
+        3..=4 => Op::SetRuntime { cgroup, runtime_us: RUNTIMES_US[rng.random_range(0..RUNTIMES_US.len())] },
+        5 => Op::SetPeriod { cgroup, period_us: PERIODS_US[rng.random_range(0..PERIODS_US.len())] },
+        6..=7 => {
+            let cgroup = if rng.random_bool(0.25) { None } else { Some(cgroup) };
+            Op::Migrate { task, cgroup }
+        },
+        8..=9 => Op::SetPolicy { task, policy: POLICIES[rng.random_range(0..POLICIES.len())] },
+        10 => {
+            let cpus: Result<CpuSet, _> = online.iter()
+                .filter(|_| rng.random_bool(0.5))
+                .fold(CpuSetUnchecked::empty(), |cpus, cpu| cpus.add_cpu(cpu))
+                .into();
+            let cpus = cpus?;
+
+            let cpus = if cpus.num_cpus() == 0 { online.clone() } else { cpus };
+            Op::SetAffinity { task, cpus }
+        },
+        _ => unreachable!(),
+    };
+
+    Ok(op)
+}
+
+#[derive(Debug)]
+struct Divergence {
+    step: usize,
+    reason: String,
+}
+
+/// Kernel side of the fuzzer: the tasks and cgroups the operations act on.
+struct Executor {
+    tasks: Vec<MyProcess>,
+}
+
+impl Executor {
+    fn new(num_tasks: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let tasks: Vec<_> = (0..num_tasks)
+            .map(|_| run_yes()).try_collect()?;
+
+        // the tasks would inherit the fuzzer's real-time policy
+        tasks.iter()
+            .try_for_each(|task| set_scheduler(task.id(), SchedPolicy::other()))?;
+
+        Ok(Self { tasks })
+    }
+
+    fn execute(&self, op: &Op) -> Result<(), String> {
+        self.__execute(op)
+            .map_err(|err| err.to_string())
+    }
+
+    fn __execute(&self, op: &Op) -> Result<(), Box<dyn std::error::Error>> {
+        match op {
+            Op::Create { cgroup } => {
+                let path = __cgroup_path(CGROUPS[*cgroup]);
+
+                // unlike create_cgroup(), this must not create missing parents
+                std::fs::create_dir(&path)
+                    .map_err(|err| format!("Error in creating directory {path}: {err}"))?;
+
+                #[cfg(feature = "cgroup_v2")]
+                __enable_cpu_contoller_v2_recursive(CGROUPS[*cgroup])?;
+            },
+            Op::Delete { cgroup } => {
+                let path = __cgroup_path(CGROUPS[*cgroup]);
+
+                std::fs::remove_dir(&path)
+                    .map_err(|err| format!("Error in destroying directory {path}: {err}"))?;
+            },
+            Op::SetRuntime { cgroup, runtime_us } =>
+                __set_cgroup_runtime_us(CGROUPS[*cgroup], *runtime_us)?,
+            Op::SetPeriod { cgroup, period_us } =>
+                __set_cgroup_period_us(CGROUPS[*cgroup], *period_us)?,
+            Op::Migrate { task, cgroup } =>
+                migrate_task_to_cgroup(cgroup_name(*cgroup), self.tasks[*task].id())?,
+            Op::SetPolicy { task, policy } =>
+                set_scheduler(self.tasks[*task].id(), policy.sched_policy())?,
+            Op::SetAffinity { task, cpus } =>
+                set_cpuset_to_pid(self.tasks[*task].id(), cpus)?,
+        }
+
+        Ok(())
+    }
+
+    // The kernel must agree with the model on the existing cgroups, their
+    // reservations and where the tasks are.
+    fn check_state(&self, model: &Model) -> Result<(), String> {
+        for (cgroup, reservation) in CGROUPS.iter().zip(model.cgroups.iter()) {
+            match (reservation, __cgroup_exists(cgroup)) {
+                (None, false) => (),
+                (None, true) => return Err(format!("cgroup {cgroup} exists")),
+                (Some(_), false) => return Err(format!("cgroup {cgroup} does not exist")),
+                (Some(reservation), true) => {
+                    let runtime_us = get_cgroup_runtime_us(cgroup).map_err(|err| err.to_string())?;
+                    let period_us = get_cgroup_period_us(cgroup).map_err(|err| err.to_string())?;
+
+                    if (runtime_us, period_us) != (reservation.runtime_us, reservation.period_us) {
+                        return Err(format!("cgroup {cgroup} has runtime/period {runtime_us}/{period_us} us, expected {}/{} us",
+                            reservation.runtime_us, reservation.period_us));
+                    }
+                },
+            }
+        }
+
+        for (i, (task, task_model)) in self.tasks.iter().zip(model.tasks.iter()).enumerate() {
+            // tasks outside the hierarchy must be in the root cgroup
+            let cgroup = cgroup_name(task_model.cgroup);
+            if !is_pid_in_cgroup(cgroup, task.id()).map_err(|err| err.to_string())? {
+                return Err(format!("task {i} is not in cgroup {cgroup}"));
+            }
+
+            let policy = get_scheduler(task.id()).map_err(|err| err.to_string())?;
+            if std::mem::discriminant(&policy) != std::mem::discriminant(&task_model.policy.sched_policy()) {
+                return Err(format!("task {i} has policy {policy}, expected {}", task_model.policy.sched_policy()));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn cleanup(self) -> Result<(), Box<dyn std::error::Error>> {
+        for mut task in self.tasks {
+            task.kill()?;
+            task.wait()?;
+        }
+
+        // Give the kernel some time to release the bandwidth of the dead
+        // DEADLINE tasks, or the next sequence may find it still allocated
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        // children first
+        CGROUPS.iter().rev()
+            .try_for_each(|cgroup| delete_cgroup(cgroup))
+    }
+}
+
+/// Replay the operations on a fresh hierarchy, returning the first operation
+/// where the kernel and the model disagree.
+fn run_sequence(ops: &[Op], num_tasks: usize) -> Result<Option<Divergence>, Box<dyn std::error::Error>> {
+    let root = Reservation { runtime_us: get_cgroup_runtime_us(".")?, period_us: get_cgroup_period_us(".")? };
+    let global = Reservation { runtime_us: get_system_rt_runtime_us()?, period_us: get_system_rt_period_us()? };
+    let online = CpuSet::all()?;
+    let fair_servers = fair_servers_ratio(&online)?;
+
+    let mut model = Model::new(root, global, online, fair_servers, num_tasks);
+    let executor = Executor::new(num_tasks)?;
+
+    let mut divergence = None;
+    for (step, op) in ops.iter().enumerate() {
+        let expected = model.apply(op);
+        let actual = executor.execute(op);
+
+        __println_debug(|| format!("{step}: {op} -> expected {expected:?}, got {actual:?}"));
+
+        let reason = match (expected, actual) {
+            (Ok(()), Err(err)) =>
+                Some(format!("expected to be accepted, but {err}")),
+            (Err(reason), Ok(())) =>
+                Some(format!("expected to be rejected ({reason}), but was accepted")),
+            _ => executor.check_state(&model)
+                .err().map(|err| format!("inconsistent state: {err}")),
+        };
+
+        if let Some(reason) = reason {
+            divergence = Some(Divergence { step, reason });
+            break;
+        }
+    }
+
+    executor.cleanup()?;
+
+    Ok(divergence)
+}
+
+/// Greedily drop single operations as long as the sequence still diverges.
+fn shrink(mut ops: Vec<Op>, mut divergence: Divergence, num_tasks: usize) -> Result<(Vec<Op>, Divergence), Box<dyn std::error::Error>> {
+    ops.truncate(divergence.step + 1);
+
+    let mut i = 0;
+    while i < ops.len() {
+        let mut candidate = ops.clone();
+        candidate.remove(i);
+
+        match run_sequence(&candidate, num_tasks)? {
+            Some(new_divergence) => {
+                candidate.truncate(new_divergence.step + 1);
+                (ops, divergence) = (candidate, new_divergence);
+
+                // the removal may enable others before this one
+                i = 0;
+            },
+            None => i += 1,
+        }
+    }
+
+    Ok((ops, divergence))
+}
+
+fn fuzz(args: &MyArgs) -> Result<String, Box<dyn std::error::Error>> {
+    if args.num_tasks == 0 {
+        Err("At least one task is required")?;
+    }
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(args.seed);
+    let online = CpuSet::all()?;
+
+    for sequence in 0..args.num_sequences {
+        let ops: Vec<_> = (0..args.length)
+            .map(|_| random_op(&mut rng, args.num_tasks, &online)).try_collect()?;
+
+        let Some(divergence) = run_sequence(&ops, args.num_tasks)? else { continue; };
+
+        let (ops, divergence) =
+            if args.no_shrink {
+                (ops[..=divergence.step].to_vec(), divergence)
+            } else {
+                shrink(ops, divergence, args.num_tasks)?
+            };
+
+        let reproducer: String = ops.iter().enumerate()
+            .map(|(step, op)| format!("\n    {step}: {op}"))
+            .collect();
+
+        Err(format!("Sequence {sequence} diverged at step {}: {}; reproducer:{reproducer}",
+            divergence.step, divergence.reason))?;
+    }
+
+    Ok(format!("{} sequences of {} operations", args.num_sequences, args.length))
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    use clap::Parser;
+
+    let args = MyArgs::parse();
+
+    mount_cgroup_fs()?;
+    mount_debug_fs()?;
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    let test_name = format!("fuzzer seed{} n{} l{}", args.seed, args.num_sequences, args.length);
+
+    batch_test_header(&test_name, "constraints");
+    batch_test_result_details(fuzz(&args))
+}
@@ -0,0 +1,286 @@
+use hcbs_test_suite::prelude::*;
+
+/// The cgroup hierarchy the fuzzer works on: two top-level groups, and two
+/// children of the first one.
+pub const CGROUPS: [&str; 4] = ["g0", "g1", "g0/g2", "g0/g3"];
+
+pub fn parent_of(cgroup: usize) -> Option<usize> {
+    match CGROUPS[cgroup].rsplit_once('/') {
+        Some((parent, _)) => CGROUPS.iter().position(|name| *name == parent),
+        None => None,
+    }
+}
+
+pub fn cgroup_name(cgroup: Option<usize>) -> &'static str {
+    cgroup.map_or(".", |cgroup| CGROUPS[cgroup])
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+pub enum Policy {
+    Other,
+    Fifo,
+    Deadline,
+}
+
+impl Policy {
+    pub fn sched_policy(&self) -> SchedPolicy {
+        match self {
+            Policy::Other => SchedPolicy::other(),
+            Policy::Fifo => SchedPolicy::FIFO(50),
+            Policy::Deadline => SchedPolicy::DEADLINE { runtime_ms: 10, deadline_ms: 100, period_ms: 100 },
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum Op {
+    Create { cgroup: usize },
+    Delete { cgroup: usize },
+    SetRuntime { cgroup: usize, runtime_us: u64 },
+    SetPeriod { cgroup: usize, period_us: u64 },
+    Migrate { task: usize, cgroup: Option<usize> },
+    SetPolicy { task: usize, policy: Policy },
+    SetAffinity { task: usize, cpus: CpuSet },
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Op::Create { cgroup } => write!(f, "create {}", CGROUPS[*cgroup]),
+            Op::Delete { cgroup } => write!(f, "delete {}", CGROUPS[*cgroup]),
+            Op::SetRuntime { cgroup, runtime_us } => write!(f, "set runtime of {} to {runtime_us} us", CGROUPS[*cgroup]),
+            Op::SetPeriod { cgroup, period_us } => write!(f, "set period of {} to {period_us} us", CGROUPS[*cgroup]),
+            Op::Migrate { task, cgroup } => write!(f, "migrate task {task} to {}", cgroup_name(*cgroup)),
+            Op::SetPolicy { task, policy } => write!(f, "set policy of task {task} to {}", policy.sched_policy()),
+            Op::SetAffinity { task, cpus } => write!(f, "set affinity of task {task} to {cpus}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+pub struct Reservation {
+    pub runtime_us: u64,
+    pub period_us: u64,
+}
+
+impl Reservation {
+    // fixed point bandwidth, as computed by the kernel's to_ratio()
+    fn ratio(&self) -> u64 {
+        (self.runtime_us << 20) / self.period_us
+    }
+}
+
+/// Fixed point bandwidth of the fair servers of the given CPUs, or zero on
+/// kernels without fair servers.
+pub fn fair_servers_ratio(cpus: &CpuSet) -> Result<u64, Box<dyn std::error::Error>> {
+    if get_fair_server_cpus().is_err() {
+        return Ok(0);
+    }
+
+    cpus.iter()
+        .map(|cpu| {
+            let bw = get_fair_server_bw(cpu)
+                .map_err(|err| format!("Cannot read the fair server's bandwidth of cpu {cpu}: {err}"))?;
+
+            Ok((bw * (1u64 << 20) as f64) as u64)
+        })
+        .sum()
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct TaskModel {
+    pub cgroup: Option<usize>,
+    pub policy: Policy,
+    pub affinity: CpuSet,
+}
+
+/// Reference model of the HCBS admission rules:
+///
+/// - a cgroup's runtime cannot exceed its period, and its bandwidth must be
+///   at least the sum of its children's (the root group's for top-level
+///   groups);
+/// - a cgroup with real-time tasks cannot have zero runtime, and real-time
+///   tasks cannot enter (or be switched to real-time inside) such a group;
+/// - SCHED_DEADLINE tasks live in the root group only, need the affinity of
+///   all the online CPUs, and share the global RT bandwidth of every CPU with
+///   the top-level cgroups' servers and the fair servers;
+/// - cgroups with tasks or children cannot be deleted.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct Model {
+    pub root: Reservation,
+    pub global: Reservation,
+    pub online: CpuSet,
+    /// fixed point bandwidth of the online CPUs' fair servers, summed
+    pub fair_servers: u64,
+    pub cgroups: [Option<Reservation>; CGROUPS.len()],
+    pub tasks: Vec<TaskModel>,
+}
+
+impl Model {
+    pub fn new(root: Reservation, global: Reservation, online: CpuSet, fair_servers: u64, num_tasks: usize) -> Self {
+        let task = TaskModel { cgroup: None, policy: Policy::Other, affinity: online.clone() };
+
+        Self {
+            root,
+            global,
+            fair_servers,
+            cgroups: [None; CGROUPS.len()],
+            tasks: vec![task; num_tasks],
+            online,
+        }
+    }
+
+    fn has_rt_tasks(&self, cgroup: usize) -> bool {
+        self.tasks.iter()
+            .any(|task| task.cgroup == Some(cgroup) && task.policy == Policy::Fifo)
+    }
+
+    fn children(&self, cgroup: Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        (0..CGROUPS.len())
+            .filter(move |child| parent_of(*child) == cgroup && self.cgroups[*child].is_some())
+    }
+
+    fn check_schedulable(&self) -> Result<(), String> {
+        let parents = std::iter::once(None)
+            .chain((0..CGROUPS.len()).filter(|cgroup| self.cgroups[*cgroup].is_some()).map(Some));
+
+        for parent in parents {
+            let total = match parent {
+                Some(parent) => self.cgroups[parent].unwrap().ratio(),
+                None => self.root.ratio(),
+            };
+
+            let sum: u64 = self.children(parent)
+                .map(|child| self.cgroups[child].unwrap().ratio())
+                .sum();
+
+            if sum > total {
+                return Err(format!("children of {} exceed its bandwidth", cgroup_name(parent)));
+            }
+        }
+
+        let num_cpus = self.online.num_cpus() as u64;
+        let servers: u64 = self.children(None)
+            .map(|cgroup| self.cgroups[cgroup].unwrap().ratio())
+            .sum::<u64>() * num_cpus;
+        let deadline: u64 = self.tasks.iter()
+            .filter(|task| task.policy == Policy::Deadline)
+            .map(|_| Reservation { runtime_us: 10_000, period_us: 100_000 }.ratio())
+            .sum();
+
+        if servers + deadline + self.fair_servers > self.global.ratio() * num_cpus {
+            return Err("servers, fair servers and DEADLINE tasks exceed the global RT bandwidth".to_owned());
+        }
+
+        Ok(())
+    }
+
+    fn set_reservation(&mut self, cgroup: usize, reservation: Reservation) -> Result<(), String> {
+        if reservation.period_us == 0 {
+            return Err("zero period".to_owned());
+        }
+
+        if reservation.runtime_us > reservation.period_us {
+            return Err("runtime greater than period".to_owned());
+        }
+
+        if reservation.runtime_us == 0 && self.has_rt_tasks(cgroup) {
+            return Err("zero runtime with real-time tasks".to_owned());
+        }
+
+        let old = self.cgroups[cgroup].replace(reservation);
+        self.check_schedulable()
+            .inspect_err(|_| self.cgroups[cgroup] = old)
+    }
+
+    /// Apply the operation if the kernel should accept it, otherwise return
+    /// the reason of the rejection and leave the model unchanged.
+    pub fn apply(&mut self, op: &Op) -> Result<(), String> {
+        let missing = |cgroup: usize| format!("{} does not exist", CGROUPS[cgroup]);
+
+        match op {
+            Op::Create { cgroup } => {
+                if self.cgroups[*cgroup].is_some() {
+                    return Err(format!("{} already exists", CGROUPS[*cgroup]));
+                }
+
+                if let Some(parent) = parent_of(*cgroup) && self.cgroups[parent].is_none() {
+                    return Err(missing(parent));
+                }
+
+                self.cgroups[*cgroup] = Some(Reservation { runtime_us: 0, period_us: self.global.period_us });
+            },
+            Op::Delete { cgroup } => {
+                if self.cgroups[*cgroup].is_none() {
+                    return Err(missing(*cgroup));
+                }
+
+                if self.children(Some(*cgroup)).next().is_some() {
+                    return Err("cgroup has children".to_owned());
+                }
+
+                if self.tasks.iter().any(|task| task.cgroup == Some(*cgroup)) {
+                    return Err("cgroup has tasks".to_owned());
+                }
+
+                self.cgroups[*cgroup] = None;
+            },
+            Op::SetRuntime { cgroup, runtime_us } => {
+                let reservation = self.cgroups[*cgroup].ok_or_else(|| missing(*cgroup))?;
+                self.set_reservation(*cgroup, Reservation { runtime_us: *runtime_us, ..reservation })?;
+            },
+            Op::SetPeriod { cgroup, period_us } => {
+                let reservation = self.cgroups[*cgroup].ok_or_else(|| missing(*cgroup))?;
+                self.set_reservation(*cgroup, Reservation { period_us: *period_us, ..reservation })?;
+            },
+            Op::Migrate { task, cgroup } => {
+                if let Some(cgroup) = cgroup {
+                    let reservation = self.cgroups[*cgroup].ok_or_else(|| missing(*cgroup))?;
+
+                    match self.tasks[*task].policy {
+                        Policy::Fifo if reservation.runtime_us == 0 =>
+                            return Err("real-time task into a zero runtime cgroup".to_owned()),
+                        Policy::Deadline =>
+                            return Err("DEADLINE task out of the root cgroup".to_owned()),
+                        _ => (),
+                    }
+                }
+
+                self.tasks[*task].cgroup = *cgroup;
+            },
+            Op::SetPolicy { task, policy } => {
+                let cgroup = self.tasks[*task].cgroup;
+
+                match (policy, cgroup) {
+                    (Policy::Fifo, Some(cgroup)) if self.cgroups[cgroup].unwrap().runtime_us == 0 =>
+                        return Err("real-time task in a zero runtime cgroup".to_owned()),
+                    (Policy::Deadline, Some(_)) =>
+                        return Err("DEADLINE task out of the root cgroup".to_owned()),
+                    (Policy::Deadline, None) if self.tasks[*task].affinity != self.online =>
+                        return Err("DEADLINE task with restricted affinity".to_owned()),
+                    _ => (),
+                }
+
+                let old = std::mem::replace(&mut self.tasks[*task].policy, *policy);
+                self.check_schedulable()
+                    .inspect_err(|_| self.tasks[*task].policy = old)?;
+            },
+            Op::SetAffinity { task, cpus } => {
+                if self.tasks[*task].policy == Policy::Deadline && cpus != &self.online {
+                    return Err("restricted affinity for a DEADLINE task".to_owned());
+                }
+
+                self.tasks[*task].affinity = cpus.clone();
+            },
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,206 @@
+use hcbs_test_suite::*;
+use hcbs_test_suite::prelude::*;
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+enum Policy {
+    Other,
+    Batch,
+    Idle,
+    Fifo,
+    RR,
+    Deadline,
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+enum CgroupState {
+    /// the root control group
+    Root,
+    /// a cgroup with zero runtime
+    RuntimeZero,
+    /// a cgroup with a reservation
+    Reserved,
+    /// a child cgroup with a reservation, inside a reserved cgroup
+    Nested,
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+enum Order {
+    PolicyThenMigrate,
+    MigrateThenPolicy,
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+enum Outcome {
+    Accepted,
+    Rejected,
+}
+
+const POLICIES: [Policy; 6] = [Policy::Other, Policy::Batch, Policy::Idle, Policy::Fifo, Policy::RR, Policy::Deadline];
+const STATES: [CgroupState; 4] = [CgroupState::Root, CgroupState::RuntimeZero, CgroupState::Reserved, CgroupState::Nested];
+const ORDERS: [Order; 2] = [Order::PolicyThenMigrate, Order::MigrateThenPolicy];
+
+impl Policy {
+    fn name(&self) -> &str {
+        match self {
+            Policy::Other => "other",
+            Policy::Batch => "batch",
+            Policy::Idle => "idle",
+            Policy::Fifo => "fifo",
+            Policy::RR => "rr",
+            Policy::Deadline => "deadline",
+        }
+    }
+
+    fn sched_policy(&self) -> SchedPolicy {
+        match self {
+            Policy::Other => SchedPolicy::other(),
+            Policy::Batch => SchedPolicy::BATCH { nice: 0 },
+            Policy::Idle => SchedPolicy::IDLE,
+            Policy::Fifo => SchedPolicy::FIFO(50),
+            Policy::RR => SchedPolicy::RR(50),
+            Policy::Deadline => SchedPolicy::DEADLINE { runtime_ms: 10, deadline_ms: 100, period_ms: 100 },
+        }
+    }
+}
+
+impl CgroupState {
+    fn name(&self) -> &str {
+        match self {
+            CgroupState::Root => "root",
+            CgroupState::RuntimeZero => "runtime_0",
+            CgroupState::Reserved => "reserved",
+            CgroupState::Nested => "nested",
+        }
+    }
+
+    /// Create the cgroups, returning the one the task is migrated to.
+    fn setup(&self) -> Result<&str, Box<dyn std::error::Error>> {
+        match self {
+            CgroupState::Root => Ok("."),
+            CgroupState::RuntimeZero => {
+                cgroup_setup("g0", 0, 100_000)?;
+                Ok("g0")
+            },
+            CgroupState::Reserved => {
+                cgroup_setup("g0", 10_000, 100_000)?;
+                Ok("g0")
+            },
+            CgroupState::Nested => {
+                cgroup_setup("g0", 20_000, 100_000)?;
+                cgroup_setup("g0/g1", 10_000, 100_000)?;
+                Ok("g0/g1")
+            },
+        }
+    }
+
+    fn cleanup(&self) -> Result<(), Box<dyn std::error::Error>> {
+        delete_cgroup("g0/g1")?;
+        delete_cgroup("g0")
+    }
+}
+
+impl Order {
+    fn name(&self) -> &str {
+        match self {
+            Order::PolicyThenMigrate => "policy_then_migrate",
+            Order::MigrateThenPolicy => "migrate_then_policy",
+        }
+    }
+}
+
+/// Declared outcome of each combination. Both orders of the operations must
+/// have the same outcome, so the order is not part of the table.
+fn expected_outcome(policy: Policy, state: CgroupState) -> Outcome {
+    use Policy::*;
+    use CgroupState::*;
+
+    match (policy, state) {
+        // the root control group accepts any task
+        (_, Root) => Outcome::Accepted,
+
+        // non real-time tasks are not subject to the RT bandwidth, so they can
+        // be moved into any cgroup, even with zero runtime
+        (Other | Batch | Idle, _) => Outcome::Accepted,
+
+        // real-time tasks cannot run in a cgroup with zero runtime, whether
+        // they are migrated or their policy is changed inside the group
+        (Fifo | RR, RuntimeZero) => Outcome::Rejected,
+        (Fifo | RR, Reserved | Nested) => Outcome::Accepted,
+
+        // SCHED_DEADLINE tasks are only allowed in the root control group
+        (Deadline, RuntimeZero | Reserved | Nested) => Outcome::Rejected,
+    }
+}
+
+fn run_combination(policy: Policy, state: CgroupState, order: Order) -> Result<(), Box<dyn std::error::Error>> {
+    let cgroup = state.setup()?;
+
+    // the task would inherit the test's real-time policy
+    let mut yes = run_yes()?;
+    set_scheduler(yes.id(), SchedPolicy::other())?;
+
+    let set_policy = || set_scheduler(yes.id(), policy.sched_policy())
+        .map_err(|err| format!("set policy {}: {err}", policy.sched_policy()));
+    let migrate = || migrate_task_to_cgroup(cgroup, yes.id())
+        .map_err(|err| format!("migrate to {cgroup}: {err}"));
+
+    let result: Result<(), String> = match order {
+        Order::PolicyThenMigrate => set_policy().and_then(|_| migrate()),
+        Order::MigrateThenPolicy => migrate().and_then(|_| set_policy()),
+    };
+
+    // a rejected operation must leave the task where it was
+    let in_cgroup = cgroup == "." || is_pid_in_cgroup(cgroup, yes.id())?;
+    let has_policy = std::mem::discriminant(&get_scheduler(yes.id())?) == std::mem::discriminant(&policy.sched_policy());
+
+    yes.kill()?;
+    yes.wait()?;
+    state.cleanup()?;
+
+    let (expected_cgroup, expected_policy) = match (&result, order) {
+        (Ok(()), _) => (true, true),
+        (Err(_), Order::PolicyThenMigrate) => (false, has_policy),
+        (Err(_), Order::MigrateThenPolicy) => (in_cgroup, false),
+    };
+
+    if (in_cgroup, has_policy) != (expected_cgroup, expected_policy) {
+        Err(format!("Inconsistent state after {}: task in cgroup {in_cgroup}, with policy {has_policy}",
+            result.as_ref().err().map_or("success", |err| err.as_str())))?;
+    }
+
+    match (expected_outcome(policy, state), result) {
+        (Outcome::Accepted, Ok(())) | (Outcome::Rejected, Err(_)) => Ok(()),
+        (Outcome::Accepted, Err(err)) =>
+            Err(format!("Expected to be accepted, but {err}"))?,
+        (Outcome::Rejected, Ok(())) =>
+            Err("Expected to be rejected, but all the operations were accepted")?,
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    mount_cgroup_fs()?;
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    // batch test utils
+    let test_category = "constraints";
+
+    for policy in POLICIES {
+        for state in STATES {
+            for order in ORDERS {
+                let test_name = format!("{}_{}_{}", policy.name(), state.name(), order.name());
+
+                batch_test_header(&test_name, test_category);
+                batch_test_result(run_combination(policy, state, order))?;
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,284 @@
+#![feature(iterator_try_collect)]
+
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use hcbs_test_suite::prelude::*;
+
+/// Wakeup latency of real-time tasks in HCBS cgroups
+///
+/// This command measures the wakeup latency of periodic SCHED_FIFO threads,
+/// in the style of cyclictest: each thread sleeps until an absolute deadline
+/// (clock_nanosleep on CLOCK_MONOTONIC) and records how late it woke up.
+///
+/// The measurement is repeated in three scenarios:
+/// - root: the threads run in the root control group (plain RT scheduling);
+/// - cgroup: the threads run in a HCBS cgroup which is never throttled;
+/// - throttled: the threads run in a HCBS cgroup whose budget is consumed by
+///   a lower priority CPU hog, so that they often wake up while the cgroup is
+///   throttled.
+///
+/// The cgroup run succeeds if its worst latency exceeds the root one by at
+/// most the given overhead. The throttled run may additionally wait for the
+/// replenishment, i.e. at most (period - runtime).
+///
+/// Constraints: runtime <= period
+#[derive(clap::Parser, Debug)]
+#[command(verbatim_doc_comment)]
+pub struct MyArgs {
+    /// cgroup's name
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    pub runtime_ms: u64,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    pub period_ms: u64,
+
+    /// number of measurement threads
+    #[arg(short = 'n', long = "num-threads", default_value = "1", value_name = "#num")]
+    pub num_threads: u64,
+
+    /// wakeup interval of the measurement threads
+    #[arg(short = 'i', long = "interval", default_value = "1000", value_name = "us: u64")]
+    pub interval_us: u64,
+
+    /// SCHED_FIFO priority of the measurement threads
+    #[arg(short = 'P', long = "priority", default_value = "80", value_name = "1-99")]
+    pub priority: i32,
+
+    /// threads' allowed cpus (threads are pinned round-robin)
+    #[arg(long = "cpu-set", value_parser = <CpuSet as std::str::FromStr>::from_str)]
+    pub cpu_set: Option<CpuSet>,
+
+    /// accepted worst-case latency overhead over the root run
+    #[arg(short = 'o', long = "max-overhead", default_value = "200", value_name = "us: u64")]
+    pub max_overhead_us: u64,
+
+    /// print the latency histograms
+    #[arg(long = "histogram")]
+    pub histogram: bool,
+
+    /// running time of each scenario
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    pub max_time: Option<u64>,
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+enum Scenario {
+    Root,
+    Cgroup,
+    Throttled,
+}
+
+impl std::fmt::Display for Scenario {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scenario::Root => write!(f, "root"),
+            Scenario::Cgroup => write!(f, "cgroup"),
+            Scenario::Throttled => write!(f, "throttled"),
+        }
+    }
+}
+
+// 1us buckets, up to 100ms
+const HISTOGRAM_BUCKET_US: u64 = 1;
+const HISTOGRAM_BUCKETS: usize = 100_000;
+
+fn __timespec_add_ns(ts: &mut libc::timespec, ns: u64) {
+    let nsec = ts.tv_nsec as u64 + ns;
+    ts.tv_sec += (nsec / 1_000_000_000) as libc::time_t;
+    ts.tv_nsec = (nsec % 1_000_000_000) as libc::c_long;
+}
+
+fn __timespec_diff_ns(later: &libc::timespec, earlier: &libc::timespec) -> i64 {
+    (later.tv_sec - earlier.tv_sec) * 1_000_000_000 + (later.tv_nsec - earlier.tv_nsec)
+}
+
+fn __clock_now() -> libc::timespec {
+    let mut now = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now); }
+    now
+}
+
+fn measurement_thread(cpu: u32, priority: i32, interval_us: u64, stop: Arc<AtomicBool>) -> Result<Histogram, Box<dyn std::error::Error + Send + Sync>> {
+    let tid = unsafe { libc::gettid() } as u32;
+    set_cpuset_to_pid(tid, &CpuSet::single(cpu)?).map_err(|err| err.to_string())?;
+    set_scheduler(tid, SchedPolicy::FIFO(priority))?;
+
+    let mut histogram = Histogram::new(HISTOGRAM_BUCKET_US, HISTOGRAM_BUCKETS);
+    let mut next = __clock_now();
+    __timespec_add_ns(&mut next, interval_us * 1000);
+
+    while !stop.load(Ordering::Relaxed) {
+        let res = unsafe {
+            libc::clock_nanosleep(libc::CLOCK_MONOTONIC, libc::TIMER_ABSTIME, &next, std::ptr::null_mut())
+        };
+
+        if res == libc::EINTR {
+            continue;
+        } else if res != 0 {
+            return Err(format!("clock_nanosleep error: {}", std::io::Error::from_raw_os_error(res)).into());
+        }
+
+        let now = __clock_now();
+        histogram.add(__timespec_diff_ns(&now, &next).max(0) as u64 / 1000);
+
+        // skip the missed activations, if any
+        while __timespec_diff_ns(&now, &next) >= 0 {
+            __timespec_add_ns(&mut next, interval_us * 1000);
+        }
+    }
+
+    set_scheduler(tid, SchedPolicy::other())?;
+
+    Ok(histogram)
+}
+
+fn run_scenario(scenario: Scenario, args: &MyArgs, cpu_set: &CpuSet, ctrlc_flag: ExitFlag) -> Result<Histogram, Box<dyn std::error::Error>> {
+    let cgroup =
+        if scenario == Scenario::Root {
+            None
+        } else {
+            let cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, true)?;
+            migrate_task_to_cgroup(&args.cgroup, std::process::id())?;
+            Some(cgroup)
+        };
+
+    // lower priority hogs consuming the cgroup's budget on every used CPU
+    let hogs: Vec<_> =
+        if scenario == Scenario::Throttled {
+            cpu_set.cpus().iter().take(args.num_threads as usize)
+                .map(|cpu| {
+                    let hog = run_yes()?;
+                    migrate_task_to_cgroup(&args.cgroup, hog.id())?;
+                    set_cpuset_to_pid(hog.id(), &CpuSet::single(*cpu)?)?;
+                    set_scheduler(hog.id(), SchedPolicy::FIFO(args.priority - 1))?;
+
+                    Ok::<_, Box<dyn std::error::Error>>(hog)
+                })
+                .try_collect()?
+        } else {
+            Vec::new()
+        };
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let threads: Vec<_> = (0..args.num_threads)
+        .map(|i| {
+            let cpu = cpu_set.cpus()[i as usize % cpu_set.num_cpus()];
+            let (priority, interval_us, stop) = (args.priority, args.interval_us, stop.clone());
+
+            std::thread::spawn(move || measurement_thread(cpu, priority, interval_us, stop))
+        })
+        .collect();
+
+    wait_loop(args.max_time, Some(ctrlc_flag))?;
+    stop.store(true, Ordering::Relaxed);
+
+    let mut histogram = Histogram::new(HISTOGRAM_BUCKET_US, HISTOGRAM_BUCKETS);
+    for thread in threads {
+        let thread_histogram = thread.join()
+            .map_err(|_| "Measurement thread panicked".to_owned())?
+            .map_err(|err| format!("Measurement thread error: {err}"))?;
+
+        histogram.merge(&thread_histogram);
+    }
+
+    hogs.into_iter()
+        .try_for_each(|mut hog| hog.kill())?;
+
+    if let Some(cgroup) = cgroup {
+        migrate_task_to_cgroup(".", std::process::id())?;
+        cgroup.destroy()?;
+    }
+
+    Ok(histogram)
+}
+
+fn print_histogram(histogram: &Histogram) {
+    println!("    Histogram (us: count):");
+    for (lower_us, count) in histogram.buckets() {
+        println!("    {lower_us:>8}: {count}");
+    }
+
+    if histogram.overflows() > 0 {
+        println!("    overflows: {}", histogram.overflows());
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = <MyArgs as clap::Parser>::parse();
+    mount_cgroup_fs()?;
+
+    if is_batch_test() && args.max_time.is_none() {
+        Err("Batch testing requires a maximum running time")?;
+    }
+
+    if args.priority < 2 || args.priority > 99 {
+        Err("Priority must be between 2 and 99")?;
+    }
+
+    if args.runtime_ms > args.period_ms {
+        Err("The cgroup's runtime cannot exceed its period")?;
+    }
+
+    let cpu_set = match &args.cpu_set {
+        Some(cpu_set) => cpu_set.clone(),
+        None => CpuSet::all()?,
+    };
+
+    let ctrlc_flag = create_ctrlc_handler()?;
+    let throttle_wait_us = (args.period_ms - args.runtime_ms) * 1000;
+
+    let mut root_max_us = None;
+    for scenario in [Scenario::Root, Scenario::Cgroup, Scenario::Throttled] {
+        if ctrlc_flag.is_exit() {
+            break;
+        }
+
+        let test_header = format!("{scenario} {} r{} p{} n{} i{}",
+            args.cgroup, args.runtime_ms, args.period_ms, args.num_threads, args.interval_us);
+        let test_header =
+            if is_batch_test() {
+                test_header
+            } else {
+                test_header + " (Ctrl+C to stop)"
+            };
+
+        batch_test_header(&test_header, "latency");
+
+        let result = run_scenario(scenario, &args, &cpu_set, ctrlc_flag.clone())
+            .and_then(|histogram| {
+                if args.histogram {
+                    print_histogram(&histogram);
+                }
+
+                let bound_us = match (scenario, root_max_us) {
+                    (Scenario::Root, _) | (_, None) => None,
+                    (Scenario::Cgroup, Some(root_max_us)) => Some(root_max_us + args.max_overhead_us),
+                    (Scenario::Throttled, Some(root_max_us)) => Some(root_max_us + args.max_overhead_us + throttle_wait_us),
+                };
+
+                if scenario == Scenario::Root {
+                    root_max_us = Some(histogram.max_us());
+                }
+
+                report_metric("max latency us", histogram.max_us() as f64);
+                report_metric("p99 latency us", histogram.percentile_us(99f64) as f64);
+
+                match bound_us {
+                    Some(bound_us) if histogram.max_us() > bound_us =>
+                        Err(format!("Worst latency {} us exceeds the bound of {bound_us} us: {histogram}", histogram.max_us()).into()),
+                    _ => Ok(format!("{histogram}")),
+                }
+            });
+
+        batch_test_result_details(result)?;
+    }
+
+    Ok(())
+}
@@ -4,7 +4,10 @@ use hcbs_test_suite::prelude::*;
 pub struct MyArgs {
     /// max running time
     #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
-    pub max_time: Option<u64>
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
 }
 
 pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
@@ -12,8 +15,8 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
         Err(format!("Batch testing requires a maximum running time"))?;
     }
 
-    let fair_server_bw = get_fair_server_avg_bw()?;
-    let error = 0.01f64; // 1% error
+    let error = args.stats.tolerance.unwrap_or(0.01); // 1% error
+    let confidence = args.stats.confidence;
 
     let test_header =
         if is_batch_test() {
@@ -24,59 +27,69 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
 
     batch_test_header(test_header, "regression");
 
+    // each CPU's SCHED_OTHER share is checked against the CPU's own server
     let result = main(args, ctrlc_flag)
-        .and_then(|used_bw| {
-            if f64::abs(used_bw - fair_server_bw) < error {
-                Ok(format!("SCHED_OTHER processes got {:.2} % of total runtime.", used_bw * 100f64))
-            } else {
-                Err(format!("Expected SCHED_OTHER tasks to use {:.2} % of total runtime, but used {:.2} %", used_bw * 100.0, fair_server_bw * 100.0).into())
-            }
-        });
-
-    if is_batch_test() {
-        batch_test_result(result)
-    } else {
-        batch_test_result_details(result)
-    }
+        .and_then(|cpu_samples| cpu_samples.into_iter()
+            .map(|(cpu, samples)| {
+                check_samples(&format!("SCHED_OTHER process on CPU {cpu}"), samples, get_fair_server_bw(cpu)?, error, confidence)
+            })
+            .try_collect()
+        );
+
+    batch_test_result_stat(result)
 }
 
-pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<f64, Box<dyn std::error::Error>> {
-    let cpus = num_cpus::get();
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<Vec<(u32, Samples)>, Box<dyn std::error::Error>> {
+    let cpus = CpuSet::all()?;
 
     migrate_task_to_cgroup(".", std::process::id())?;
-    let fifo_processes: Vec<_> = (0..cpus).map(|_| cpu_hog()).try_collect()?;
-    let non_fifo_processes: Vec<_> = (0..cpus).map(|_| cpu_hog()).try_collect()?;
+    let fifo_processes: Vec<_> = cpus.cpus().iter().map(|_| cpu_hog()).try_collect()?;
+    let non_fifo_processes: Vec<_> = cpus.cpus().iter().map(|_| cpu_hog()).try_collect()?;
 
     set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
     non_fifo_processes.iter()
-        .enumerate()
-        .try_for_each(|(i, proc)| {
-            set_cpuset_to_pid(proc.id(), &CpuSet::single(i as u32)?)
+        .zip(cpus.cpus())
+        .try_for_each(|(proc, cpu)| {
+            set_cpuset_to_pid(proc.id(), &CpuSet::single(*cpu)?)
         })?;
 
     fifo_processes.iter()
-        .enumerate()
-        .try_for_each::<_, Result<_, Box<dyn std::error::Error>>>(|(i, proc)| {
-            set_cpuset_to_pid(proc.id(), &CpuSet::single(i as u32)?)?;
+        .zip(cpus.cpus())
+        .try_for_each::<_, Result<_, Box<dyn std::error::Error>>>(|(proc, cpu)| {
+            set_cpuset_to_pid(proc.id(), &CpuSet::single(*cpu)?)?;
             set_scheduler(proc.id(), SchedPolicy::RR(50))?;
 
             Ok(())
         })?;
 
-    wait_loop(args.max_time, ctrlc_flag)?;
-
-    let fifo_total_usage = 
-        fifo_processes.iter()
-        .map(|proc| get_process_total_runtime_usage(proc.id()))
-        .sum::<Result<f64, _>>()?;
-
-    let non_fifo_total_usage = 
-        non_fifo_processes.iter()
-        .map(|proc| get_process_total_runtime_usage(proc.id()))
-        .sum::<Result<f64, _>>()?;
-
-    let non_fifo_ratio =
-        non_fifo_total_usage / (non_fifo_total_usage + fifo_total_usage);
+    // one pair of meters per CPU
+    let mut meters: Vec<_> = fifo_processes.iter()
+        .zip(non_fifo_processes.iter())
+        .map(|(fifo, non_fifo)| Ok::<_, Box<dyn std::error::Error>>((
+            BandwidthMeter::new(&[fifo.id()])?,
+            BandwidthMeter::new(&[non_fifo.id()])?,
+        )))
+        .try_collect()?;
+
+    let samples = collect_window_samples_multi(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || {
+            meters.iter_mut()
+                .map(|(fifo_meter, non_fifo_meter)| {
+                    let (fifo_usage, _) = fifo_meter.sample_runtime()?;
+                    let (non_fifo_usage, _) = non_fifo_meter.sample_runtime()?;
+
+                    if non_fifo_usage + fifo_usage <= 0f64 {
+                        Err("The processes did not run in the measurement window")?;
+                    }
+
+                    Ok(non_fifo_usage / (non_fifo_usage + fifo_usage))
+                })
+                .try_collect()
+        }
+    )?;
 
     fifo_processes.into_iter()
         .try_for_each(|mut proc| proc.kill())?;
@@ -84,5 +97,5 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<f64, Box<dyn s
     non_fifo_processes.into_iter()
         .try_for_each(|mut proc| proc.kill())?;
 
-    Ok(non_fifo_ratio)
+    Ok(cpus.cpus().iter().copied().zip(samples).collect())
 }
\ No newline at end of file
@@ -0,0 +1,403 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::ValueEnum, Debug)]
+#[derive(Clone, Copy)]
+pub enum SpawnMode {
+    Fork,
+    Thread,
+}
+
+impl std::fmt::Display for SpawnMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpawnMode::Fork => write!(f, "fork"),
+            SpawnMode::Thread => write!(f, "thread"),
+        }
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup's name
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    pub runtime_ms: u64,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    pub period_ms: u64,
+
+    /// how the real-time task spawns its children [default: both]
+    #[arg(short = 'm', long = "mode", value_enum)]
+    pub mode: Option<SpawnMode>,
+
+    /// children spawned per second in the bandwidth test
+    #[arg(long = "rate", default_value = "1000", value_name = "u64")]
+    pub rate: u64,
+
+    /// cpu time consumed by each child in the bandwidth test
+    #[arg(long = "child-work", default_value = "200", value_name = "us: u64")]
+    pub child_work_us: u64,
+
+    /// cpu to run the tasks on
+    #[arg(long = "cpu", default_value = "0", value_name = "u32")]
+    pub cpu: u32,
+
+    /// max running time of each bandwidth test
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
+}
+
+// Shared between the test, the spawner and the spawner's children.
+struct Shared {
+    start: AtomicBool,
+    stop: AtomicBool,
+    child_tid: AtomicU32,
+    child_work_ns: AtomicU64,
+    num_children: AtomicU64,
+    active_children: AtomicU64,
+}
+
+impl Shared {
+    fn new() -> Result<SharedMemory<Shared>, Box<dyn std::error::Error>> {
+        SharedMemory::new(Shared {
+            start: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            child_tid: AtomicU32::new(0),
+            child_work_ns: AtomicU64::new(0),
+            num_children: AtomicU64::new(0),
+            active_children: AtomicU64::new(0),
+        })
+    }
+
+    fn wait(flag: &AtomicBool) {
+        while !flag.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+// Stops the spawner's children when the test ends, also on errors, before the
+// spawner is killed: an orphaned child would keep its cgroup busy.
+struct StopGuard<'a>(&'a Shared);
+
+impl Drop for StopGuard<'_> {
+    fn drop(&mut self) {
+        self.0.stop.store(true, Ordering::Release);
+
+        let start = std::time::Instant::now();
+        while self.0.active_children.load(Ordering::Acquire) > 0 && start.elapsed() < Duration::from_secs(1) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+/// Spawn a child running `fun`, either a process or a detached thread.
+fn spawn(mode: SpawnMode, shared: &Shared, fun: extern "C" fn(*mut libc::c_void) -> *mut libc::c_void) -> bool {
+    let arg = shared as *const Shared as *mut libc::c_void;
+
+    // decremented by the child when done
+    shared.active_children.fetch_add(1, Ordering::AcqRel);
+
+    let res = match mode {
+        SpawnMode::Fork => match unsafe { libc::fork() } {
+            -1 => false,
+            0 => { fun(arg); unsafe { libc::_exit(0) } },
+            _ => true,
+        },
+        SpawnMode::Thread => unsafe {
+            let mut attr: libc::pthread_attr_t = std::mem::zeroed();
+            let mut thread: libc::pthread_t = std::mem::zeroed();
+
+            let res = libc::pthread_attr_init(&mut attr) == 0
+                && libc::pthread_attr_setdetachstate(&mut attr, libc::PTHREAD_CREATE_DETACHED) == 0
+                && libc::pthread_create(&mut thread, &attr, fun, arg) == 0;
+            libc::pthread_attr_destroy(&mut attr);
+
+            res
+        },
+    };
+
+    if !res {
+        shared.active_children.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    res
+}
+
+// Wait for every child to terminate.
+fn reap_all(mode: SpawnMode, shared: &Shared) {
+    match mode {
+        SpawnMode::Fork => while unsafe { libc::waitpid(-1, std::ptr::null_mut(), 0) } > 0 {},
+        SpawnMode::Thread => while shared.active_children.load(Ordering::Acquire) > 0 {
+            std::thread::sleep(Duration::from_millis(1));
+        },
+    }
+}
+
+fn reap_terminated(mode: SpawnMode) {
+    if let SpawnMode::Fork = mode {
+        while unsafe { libc::waitpid(-1, std::ptr::null_mut(), libc::WNOHANG) } > 0 {}
+    }
+}
+
+// Publish the child's tid and wait for the end of the test.
+extern "C" fn waiting_child(arg: *mut libc::c_void) -> *mut libc::c_void {
+    let shared = unsafe { &*(arg as *const Shared) };
+
+    shared.child_tid.store(unsafe { libc::gettid() } as u32, Ordering::Release);
+    Shared::wait(&shared.stop);
+    shared.active_children.fetch_sub(1, Ordering::AcqRel);
+
+    std::ptr::null_mut()
+}
+
+fn inheritance_spawner(shared: &Shared, mode: SpawnMode) -> i32 {
+    Shared::wait(&shared.start);
+
+    if !spawn(mode, shared, waiting_child) {
+        return 1;
+    }
+
+    reap_all(mode, shared);
+    0
+}
+
+extern "C" fn working_child(arg: *mut libc::c_void) -> *mut libc::c_void {
+    let shared = unsafe { &*(arg as *const Shared) };
+
+    busy_cpu_time(shared.child_work_ns.load(Ordering::Acquire));
+    shared.num_children.fetch_add(1, Ordering::AcqRel);
+    shared.active_children.fetch_sub(1, Ordering::AcqRel);
+
+    std::ptr::null_mut()
+}
+
+fn sleep_until_ns(ns: u64) {
+    let time = libc::timespec {
+        tv_sec: (ns / 1_000_000_000) as libc::time_t,
+        tv_nsec: (ns % 1_000_000_000) as libc::c_long,
+    };
+
+    // interrupted sleeps are not resumed, the caller checks the time again
+    unsafe { libc::clock_nanosleep(libc::CLOCK_MONOTONIC, libc::TIMER_ABSTIME, &time, std::ptr::null_mut()); }
+}
+
+// Spawn short-lived children at the given rate, sleeping in between so that
+// the children, which share the spawner's priority, get to run.
+fn bandwidth_spawner(shared: &Shared, mode: SpawnMode, rate: u64) -> i32 {
+    Shared::wait(&shared.start);
+
+    let interval_ns = 1_000_000_000 / rate.max(1);
+    let mut next_ns = now_ns(libc::CLOCK_MONOTONIC);
+
+    while !shared.stop.load(Ordering::Acquire) {
+        sleep_until_ns(next_ns);
+
+        let now = now_ns(libc::CLOCK_MONOTONIC);
+        if now >= next_ns {
+            if !spawn(mode, shared, working_child) {
+                return 1;
+            }
+
+            // do not try to catch up after being throttled
+            next_ns = (next_ns + interval_ns).max(now);
+        }
+
+        reap_terminated(mode);
+    }
+
+    reap_all(mode, shared);
+    0
+}
+
+/// Cpu time consumed by a process, its threads and its reaped children.
+fn tree_runtime_secs(pid: u32) -> Result<f64, Box<dyn std::error::Error>> {
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as f64;
+
+    let stats = std::fs::read_to_string(format!("/proc/{pid}/stat"))
+        .map_err(|err| format!("Error in reading /proc/{pid}/stat: {err}"))?;
+
+    // skip the command name, which may contain spaces
+    let stats: Vec<_> = stats.rsplit_once(')')
+        .ok_or_else(|| format!("Error in parsing /proc/{pid}/stat"))?
+        .1.split_whitespace().collect();
+
+    // utime, stime, cutime and cstime
+    let ticks: u64 = stats.get(11..15)
+        .ok_or_else(|| format!("Error in parsing /proc/{pid}/stat"))?
+        .iter()
+        .map(|ticks| ticks.parse::<u64>()).try_collect::<Vec<_>>()?
+        .iter().sum();
+
+    Ok(ticks as f64 / ticks_per_second)
+}
+
+fn check_inheritance(args: &MyArgs, mode: SpawnMode, reset_on_fork: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let _cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, true)?;
+    let shared = Shared::new()?;
+
+    let mut spawner = fork_process(|| inheritance_spawner(&shared, mode))?;
+    let _stop = StopGuard(&shared);
+    let policy = SchedPolicy::FIFO(50);
+    let flags: &[SchedFlag] = if reset_on_fork { &[SchedFlag::ResetOnFork] } else { &[] };
+
+    migrate_task_to_cgroup(&args.cgroup, spawner.id())?;
+    set_cpuset_to_pid(spawner.id(), &CpuSet::single(args.cpu)?)?;
+    set_scheduler_with_flags(spawner.id(), policy, flags)?;
+    shared.start.store(true, Ordering::Release);
+
+    let start = std::time::Instant::now();
+    while shared.child_tid.load(Ordering::Acquire) == 0 {
+        if start.elapsed() > Duration::from_secs(1) {
+            Err(format!("The {mode} child was not spawned"))?;
+        }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    let child = shared.child_tid.load(Ordering::Acquire);
+    let check = || -> Result<(), Box<dyn std::error::Error>> {
+        let in_cgroup = match mode {
+            SpawnMode::Fork => is_pid_in_cgroup(&args.cgroup, child)?,
+            SpawnMode::Thread => {
+                let spawner_cgroups = std::fs::read_to_string(format!("/proc/{}/cgroup", spawner.id()))?;
+                let child_cgroups = std::fs::read_to_string(format!("/proc/{}/task/{child}/cgroup", spawner.id()))?;
+
+                is_pid_in_cgroup(&args.cgroup, spawner.id())? && spawner_cgroups == child_cgroups
+            },
+        };
+
+        if !in_cgroup {
+            Err(format!("The {mode} child is not in cgroup {}", args.cgroup))?;
+        }
+
+        // SCHED_RESET_ON_FORK children go back to SCHED_OTHER
+        let expected = if reset_on_fork { SchedPolicy::other() } else { policy };
+        let child_policy = get_scheduler(child)?;
+        if child_policy != expected {
+            Err(format!("The {mode} child has policy {child_policy}, expected {expected}"))?;
+        }
+
+        Ok(())
+    };
+
+    let result = check();
+
+    shared.stop.store(true, Ordering::Release);
+    match spawner.wait()? {
+        Some(0) => result,
+        status => Err(format!("Spawner terminated with status {status:?}"))?,
+    }
+}
+
+fn bandwidth(args: &MyArgs, mode: SpawnMode, ctrlc_flag: Option<ExitFlag>) -> Result<Vec<StatReport>, Box<dyn std::error::Error>> {
+    // the children must demand more than the cgroup's bandwidth, so that
+    // they consume all of it
+    let expected_bw = args.runtime_ms as f64 / args.period_ms as f64;
+    if (args.rate * args.child_work_us) as f64 / 1_000_000f64 <= expected_bw {
+        Err("The children's demand (rate * child work) must exceed the cgroup's bandwidth")?;
+    }
+
+    let error = args.stats.tolerance.unwrap_or(0.01); // 1% of the cpu
+
+    let _cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, true)?;
+    let shared = Shared::new()?;
+
+    shared.child_work_ns.store(args.child_work_us * 1000, Ordering::Release);
+    let mut spawner = fork_process(|| bandwidth_spawner(&shared, mode, args.rate))?;
+    let _stop = StopGuard(&shared);
+
+    migrate_task_to_cgroup(&args.cgroup, spawner.id())?;
+    set_cpuset_to_pid(spawner.id(), &CpuSet::single(args.cpu)?)?;
+    set_scheduler(spawner.id(), SchedPolicy::FIFO(50))?;
+    shared.start.store(true, Ordering::Release);
+
+    let mut last_runtime = tree_runtime_secs(spawner.id())?;
+    let mut last_time = std::time::Instant::now();
+    let mut last_children = shared.num_children.load(Ordering::Acquire);
+    let samples = collect_window_samples(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || {
+            let (runtime, time) = (tree_runtime_secs(spawner.id())?, std::time::Instant::now());
+            let bw = (runtime - last_runtime) / time.duration_since(last_time).as_secs_f64();
+            (last_runtime, last_time) = (runtime, time);
+
+            // the children must run and terminate during the measurement
+            let children = shared.num_children.load(Ordering::Acquire);
+            if children == last_children {
+                Err(format!("No {mode} child completed in the measurement window"))?;
+            }
+
+            __println_debug(|| format!("{} {mode} children completed in the window", children - last_children));
+            last_children = children;
+
+            Ok(bw)
+        }
+    )?;
+
+    shared.stop.store(true, Ordering::Release);
+    if spawner.wait()? != Some(0) {
+        Err(format!("The spawner could not spawn its {mode} children"))?;
+    }
+
+    Ok(vec![check_samples(&format!("Spawner and {mode} children"), samples, expected_bw, error, args.stats.confidence)?])
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    if is_batch_test() && args.max_time.is_none() {
+        Err("Batch testing requires a maximum running time")?;
+    }
+
+    let modes = match args.mode {
+        Some(mode) => vec![mode],
+        None => vec![SpawnMode::Fork, SpawnMode::Thread],
+    };
+
+    let ctrlc_flag = match ctrlc_flag {
+        Some(exit) => exit,
+        None => create_ctrlc_handler()?,
+    };
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    for mode in modes.iter().copied() {
+        for reset_on_fork in [false, true] {
+            let test_header = format!("fork_inheritance {mode}{}",
+                if reset_on_fork { " reset_on_fork" } else { "" });
+
+            batch_test_header(&test_header, "regression");
+            batch_test_result(check_inheritance(&args, mode, reset_on_fork))?;
+        }
+    }
+
+    for mode in modes {
+        let test_header = format!("fork_bandwidth {mode} r{} p{} rate{} work{}",
+            args.runtime_ms, args.period_ms, args.rate, args.child_work_us);
+        let test_header =
+            if is_batch_test() {
+                test_header
+            } else {
+                test_header + " (Ctrl+C to stop)"
+            };
+
+        batch_test_header(&test_header, "regression");
+        batch_test_result_stat(bandwidth(&args, mode, Some(ctrlc_flag.clone())))?;
+    }
+
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 #![feature(iterator_try_collect)]
 
 mod fair_server;
+mod fork;
+mod pi_mutex;
+mod priority_order;
 mod sched_deadline;
 mod sched_fifo;
 
@@ -36,6 +39,41 @@ pub enum Command {
     /// Constraints: runtime <= period
     #[command(name = "fifo", verbatim_doc_comment)]
     SchedFifo(sched_fifo::MyArgs),
+
+    /// Priority inheritance across cgroups
+    ///
+    /// A low priority task in a throttled cgroup holds a PTHREAD_PRIO_INHERIT
+    /// mutex that a high priority task, in the root or another cgroup, waits
+    /// for, while a medium priority task hogs the holder's cgroup. The test
+    /// expects the high priority task's blocking time to be bounded by the
+    /// time the holder needs to complete its critical section with the
+    /// cgroup's bandwidth.
+    #[command(name = "pi-mutex", verbatim_doc_comment)]
+    PiMutex(pi_mutex::MyArgs),
+
+    /// Fork and thread inheritance of real-time tasks in cgroups
+    ///
+    /// A SCHED_FIFO task inside a cgroup spawns children, either with fork or
+    /// pthread_create. The test checks that the children inherit the cgroup
+    /// and the policy/priority, or revert to SCHED_OTHER with
+    /// SCHED_RESET_ON_FORK. Then the task spawns short-lived cpu-bound
+    /// children at a high rate, sleeping in between, and the test expects
+    /// children to complete in every measurement window and the task and its
+    /// children together to consume the cgroup's bandwidth, no more.
+    ///
+    /// Constraints: runtime <= period, rate * child-work > runtime / period
+    #[command(name = "fork", verbatim_doc_comment)]
+    Fork(fork::MyArgs),
+
+    /// Fixed priority ordering inside a cgroup
+    ///
+    /// A number of SCHED_FIFO tasks with distinct priorities periodically
+    /// consume cpu time on the same cpu, inside a throttled cgroup. The
+    /// scheduler's events are traced, and the test expects that a lower
+    /// priority task never executes while a higher priority one is runnable
+    /// and the cgroup's server has budget.
+    #[command(name = "priority-order", verbatim_doc_comment)]
+    PriorityOrder(priority_order::MyArgs),
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -48,5 +86,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         FairServer(args) => fair_server::batch_runner(args, None).map(|_| ()),
         SchedDeadline(args) => sched_deadline::batch_runner(args, None).map(|_| ()),
         SchedFifo(args) => sched_fifo::batch_runner(args, None).map(|_| ()),
+        PiMutex(args) => pi_mutex::batch_runner(args, None),
+        Fork(args) => fork::batch_runner(args, None),
+        PriorityOrder(args) => priority_order::batch_runner(args, None),
     }
 }
\ No newline at end of file
@@ -0,0 +1,262 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup of the low priority lock holder
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    pub runtime_ms: u64,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    pub period_ms: u64,
+
+    /// cgroup of the high priority waiter, with the same reservation [default: root cgroup]
+    #[arg(long = "high-cgroup", value_name = "name")]
+    pub high_cgroup: Option<String>,
+
+    /// critical section length, in cpu time
+    #[arg(short = 's', long = "critical-section", default_value = "5", value_name = "ms: u64")]
+    pub critical_section_ms: u64,
+
+    /// number of times the high priority task takes the lock
+    #[arg(short = 'n', long = "num-locks", default_value = "20", value_name = "u64")]
+    pub num_locks: u64,
+
+    /// maximum blocking time [default: (⌈critical-section / runtime⌉ + 1) * period]
+    #[arg(short = 'b', long = "bound", value_name = "ms: u64")]
+    pub bound_ms: Option<u64>,
+
+    /// cpu to run the tasks on
+    #[arg(long = "cpu", default_value = "0", value_name = "u32")]
+    pub cpu: u32,
+}
+
+const MAX_LOCKS: usize = 1024;
+
+// Shared between the test and its forked tasks, holding a process-shared
+// PTHREAD_PRIO_INHERIT mutex.
+#[repr(C)]
+struct Shared {
+    mutex: libc::pthread_mutex_t,
+    start: AtomicBool,
+    stop: AtomicBool,
+    locked: AtomicBool,
+    num_done: AtomicU64,
+    blocking_ns: [AtomicU64; MAX_LOCKS],
+}
+
+impl Shared {
+    // The mutex must be initialized in place, once in shared memory.
+    fn new() -> Result<SharedMemory<Shared>, Box<dyn std::error::Error>> {
+        let shared = SharedMemory::new(Shared {
+            mutex: unsafe { std::mem::zeroed() },
+            start: AtomicBool::new(false),
+            stop: AtomicBool::new(false),
+            locked: AtomicBool::new(false),
+            num_done: AtomicU64::new(0),
+            blocking_ns: [const { AtomicU64::new(0) }; MAX_LOCKS],
+        })?;
+
+        unsafe {
+            let mut attr: libc::pthread_mutexattr_t = std::mem::zeroed();
+            let res = [
+                libc::pthread_mutexattr_init(&mut attr),
+                libc::pthread_mutexattr_setpshared(&mut attr, libc::PTHREAD_PROCESS_SHARED),
+                libc::pthread_mutexattr_setprotocol(&mut attr, libc::PTHREAD_PRIO_INHERIT),
+                libc::pthread_mutex_init(shared.mutex(), &attr),
+                libc::pthread_mutexattr_destroy(&mut attr),
+            ];
+
+            if let Some(err) = res.iter().find(|res| **res != 0) {
+                Err(format!("Error in creating the PI mutex: {}", std::io::Error::from_raw_os_error(*err)))?;
+            }
+        }
+
+        Ok(shared)
+    }
+
+    fn mutex(&self) -> *mut libc::pthread_mutex_t {
+        &self.mutex as *const _ as *mut _
+    }
+
+    fn wait_start(&self) {
+        while !self.start.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+// Repeatedly take the lock and hold it for the critical section.
+fn low_task(shared: &Shared, critical_section_ns: u64) -> i32 {
+    shared.wait_start();
+
+    while !shared.stop.load(Ordering::Acquire) {
+        unsafe { libc::pthread_mutex_lock(shared.mutex()); }
+        shared.locked.store(true, Ordering::Release);
+
+        busy_cpu_time(critical_section_ns);
+
+        shared.locked.store(false, Ordering::Release);
+        unsafe { libc::pthread_mutex_unlock(shared.mutex()); }
+
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    0
+}
+
+// Preempt the lock holder and hog its cgroup while the lock is held. Without
+// priority inheritance, the lock holder would never run again.
+fn medium_task(shared: &Shared) -> i32 {
+    shared.wait_start();
+
+    while !shared.stop.load(Ordering::Acquire) {
+        if !shared.locked.load(Ordering::Acquire) {
+            std::thread::sleep(Duration::from_micros(100));
+            continue;
+        }
+
+        while shared.locked.load(Ordering::Acquire) && !shared.stop.load(Ordering::Acquire) {
+            std::hint::spin_loop();
+        }
+    }
+
+    0
+}
+
+// Wait for the lock to be held, then measure how long it takes to get it.
+fn high_task(shared: &Shared, num_locks: usize, period_ms: u64) -> i32 {
+    shared.wait_start();
+
+    for i in 0..num_locks {
+        while !shared.locked.load(Ordering::Acquire) {
+            if shared.stop.load(Ordering::Acquire) { return 1; }
+            std::thread::sleep(Duration::from_micros(100));
+        }
+
+        let start = now_ns(libc::CLOCK_MONOTONIC);
+        unsafe { libc::pthread_mutex_lock(shared.mutex()); }
+        let end = now_ns(libc::CLOCK_MONOTONIC);
+        unsafe { libc::pthread_mutex_unlock(shared.mutex()); }
+
+        shared.blocking_ns[i].store(end - start, Ordering::Release);
+        shared.num_done.fetch_add(1, Ordering::AcqRel);
+
+        // hit the lock holder at different points of its server's period
+        std::thread::sleep(Duration::from_millis(1 + (i as u64 * 7) % period_ms));
+    }
+
+    0
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    let test_header = format!("pi_mutex c{} r{} p{} s{} high:{}",
+        args.cgroup, args.runtime_ms, args.period_ms, args.critical_section_ms,
+        args.high_cgroup.as_deref().unwrap_or("."));
+
+    batch_test_header(&test_header, "regression");
+    batch_test_result_details(main(args, ctrlc_flag))
+}
+
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<String, Box<dyn std::error::Error>> {
+    if args.num_locks == 0 || args.num_locks as usize > MAX_LOCKS {
+        Err(format!("The number of locks must be between 1 and {MAX_LOCKS}"))?;
+    }
+
+    if args.runtime_ms == 0 {
+        Err("The lock holder's cgroup must have some runtime")?;
+    }
+
+    // the lock holder needs ⌈cs / runtime⌉ periods to complete its critical
+    // section, plus one for the budget already consumed by the medium task
+    let bound_ms = args.bound_ms
+        .unwrap_or((args.critical_section_ms.div_ceil(args.runtime_ms) + 1) * args.period_ms);
+    let cpu_set = CpuSet::single(args.cpu)?;
+
+    let (runtime_us, period_us) = (args.runtime_ms * 1000, args.period_ms * 1000);
+    let _cgroup = MyCgroup::new(&args.cgroup, runtime_us, period_us, true)?;
+    let _high_cgroup = args.high_cgroup.as_deref()
+        .map(|high_cgroup| MyCgroup::new(high_cgroup, runtime_us, period_us, true))
+        .transpose()?;
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    let shared = Shared::new()?;
+    let num_locks = args.num_locks as usize;
+    let critical_section_ns = args.critical_section_ms * 1_000_000;
+
+    let tasks = [
+        (fork_process(|| low_task(&shared, critical_section_ns))?, args.cgroup.as_str(), SchedPolicy::FIFO(10)),
+        (fork_process(|| medium_task(&shared))?, args.cgroup.as_str(), SchedPolicy::FIFO(50)),
+        (fork_process(|| high_task(&shared, num_locks, args.period_ms))?, args.high_cgroup.as_deref().unwrap_or("."), SchedPolicy::FIFO(90)),
+    ];
+
+    tasks.iter()
+        .try_for_each(|(task, cgroup, policy)| {
+            migrate_task_to_cgroup(cgroup, task.id())?;
+            set_cpuset_to_pid(task.id(), &cpu_set)?;
+            set_scheduler(task.id(), *policy)?;
+
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+
+    let ctrlc_flag = match ctrlc_flag {
+        Some(flag) => flag,
+        None => create_ctrlc_handler()?,
+    };
+
+    shared.start.store(true, Ordering::Release);
+
+    // each lock takes at most the bound plus the high priority task's sleep,
+    // anything much longer is an unbounded inversion
+    let timeout = Duration::from_millis(args.num_locks * 10 * (bound_ms + args.period_ms));
+    let start = std::time::Instant::now();
+    while shared.num_done.load(Ordering::Acquire) < args.num_locks
+        && start.elapsed() < timeout
+        && !ctrlc_flag.is_exit()
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let elapsed = start.elapsed();
+
+    shared.stop.store(true, Ordering::Release);
+    for (mut task, _, _) in tasks {
+        task.kill()?;
+    }
+
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+
+    let num_done = shared.num_done.load(Ordering::Acquire) as usize;
+    if num_done < num_locks && elapsed >= timeout {
+        Err(format!("Unbounded priority inversion: the high priority task got the lock {num_done}/{num_locks} times in {:.1} s",
+            elapsed.as_secs_f64()))?;
+    }
+
+    // a partial sample does not prove the bound
+    if num_done < num_locks {
+        Err(format!("Interrupted after {num_done}/{num_locks} locks"))?;
+    }
+
+    let blocking_ms: Vec<_> = shared.blocking_ns[..num_done].iter()
+        .map(|blocking_ns| blocking_ns.load(Ordering::Acquire) as f64 / 1_000_000f64)
+        .collect();
+
+    let (worst, max_ms) = blocking_ms.iter().copied().enumerate()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .unwrap();
+    let mean_ms = blocking_ms.iter().sum::<f64>() / num_done as f64;
+
+    if max_ms > bound_ms as f64 {
+        Err(format!("Blocking time {max_ms:.2} ms (lock {worst}) exceeds the bound of {bound_ms} ms, mean {mean_ms:.2} ms"))?;
+    }
+
+    Ok(format!("blocking time max {max_ms:.2} ms, mean {mean_ms:.2} ms, bound {bound_ms} ms"))
+}
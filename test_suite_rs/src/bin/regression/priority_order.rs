@@ -0,0 +1,161 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup's name
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    pub runtime_ms: u64,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    pub period_ms: u64,
+
+    /// number of tasks, with SCHED_FIFO priorities 10, 20, ...
+    #[arg(short = 'n', long = "num-tasks", default_value = "3", value_name = "u64")]
+    pub num_tasks: u64,
+
+    /// cpu time consumed by each task per activation
+    #[arg(long = "work", default_value = "2", value_name = "ms: u64")]
+    pub work_ms: u64,
+
+    /// sleep time between activations, multiplied by the task's index
+    #[arg(long = "sleep", default_value = "3", value_name = "ms: u64")]
+    pub sleep_ms: u64,
+
+    /// time a higher priority task may wait for the lower one to be preempted
+    #[arg(long = "epsilon", default_value = "200", value_name = "us: u64")]
+    pub epsilon_us: u64,
+
+    /// cpu to run the tasks on
+    #[arg(long = "cpu", default_value = "0", value_name = "u32")]
+    pub cpu: u32,
+
+    /// traced running time
+    #[arg(short = 't', long = "max-time", default_value = "5", value_name = "sec: u64")]
+    pub max_time: u64,
+}
+
+// Shared between the test and its forked tasks.
+struct Shared {
+    start: AtomicBool,
+    stop: AtomicBool,
+}
+
+// Periodically consume some cpu time, so that the tasks' activations overlap
+// in every possible order, also across the server's throttling.
+fn periodic_task(shared: &Shared, work: Duration, sleep: Duration) -> i32 {
+    while !shared.start.load(Ordering::Acquire) {
+        std::thread::sleep(Duration::from_millis(1));
+    }
+
+    while !shared.stop.load(Ordering::Acquire) {
+        busy_cpu_time(work.as_nanos() as u64);
+        std::thread::sleep(sleep);
+    }
+
+    0
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    let test_header = format!("priority_order c{} r{} p{} n{} work{} sleep{}",
+        args.cgroup, args.runtime_ms, args.period_ms, args.num_tasks, args.work_ms, args.sleep_ms);
+
+    batch_test_header(&test_header, "regression");
+    batch_test_result_details(main(args, ctrlc_flag))
+}
+
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<String, Box<dyn std::error::Error>> {
+    if args.num_tasks < 2 || args.num_tasks > 9 {
+        Err("The number of tasks must be between 2 and 9")?;
+    }
+
+    let cpu_set = CpuSet::single(args.cpu)?;
+    let _cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, true)?;
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    let shared = SharedMemory::new(Shared {
+        start: AtomicBool::new(false),
+        stop: AtomicBool::new(false),
+    })?;
+
+    let tasks: Vec<_> = (0..args.num_tasks)
+        .map(|i| {
+            let priority = 10 * (i as i32 + 1);
+            let sleep = Duration::from_millis(args.sleep_ms * (i + 1));
+            let task = fork_process(|| periodic_task(&shared, Duration::from_millis(args.work_ms), sleep))?;
+
+            migrate_task_to_cgroup(&args.cgroup, task.id())?;
+            set_cpuset_to_pid(task.id(), &cpu_set)?;
+            set_scheduler(task.id(), SchedPolicy::FIFO(priority))?;
+
+            Ok::<_, Box<dyn std::error::Error>>((task, priority as u32))
+        })
+        .try_collect()?;
+
+    let tracer = Tracer::start(&cpu_set, 16384)?;
+    shared.start.store(true, Ordering::Release);
+
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(args.max_time)
+        && !ctrlc_flag.as_ref().is_some_and(|flag| flag.is_exit())
+    {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    shared.stop.store(true, Ordering::Release);
+    let events = tracer.stop()?;
+
+    let priorities: Vec<_> = tasks.iter()
+        .map(|(task, priority)| (task.id(), *priority))
+        .collect();
+
+    for (mut task, _) in tasks {
+        task.kill()?;
+    }
+
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+
+    // cpu time of each task, from the highest priority one
+    let pids: Vec<_> = priorities.iter().map(|(pid, _)| *pid).collect();
+    let intervals = execution_intervals(&events, &pids);
+    let usage: Vec<_> = priorities.iter().rev()
+        .map(|(pid, priority)| {
+            let used_ns: u64 = intervals.iter()
+                .filter(|interval| interval.pid == *pid)
+                .map(|interval| interval.end_ns - interval.start_ns)
+                .sum();
+
+            (*priority, used_ns)
+        })
+        .collect();
+
+    if usage[0].1 == 0 {
+        Err("No execution of the highest priority task was traced")?;
+    }
+
+    let inversions = verify_priority_order(&events, &priorities, args.epsilon_us * 1000);
+    if !inversions.is_empty() {
+        let shown: Vec<_> = inversions.iter().take(5)
+            .map(|inversion| inversion.to_string())
+            .collect();
+
+        Err(format!("Priority order violated {} times: {}{}",
+            inversions.len(), shown.join("; "),
+            if inversions.len() > shown.len() { "; ..." } else { "" }))?;
+    }
+
+    let usage: Vec<_> = usage.iter()
+        .map(|(priority, used_ns)| format!("prio {priority}: {} ms", used_ns / 1_000_000))
+        .collect();
+
+    Ok(format!("no priority inversions in {} execution intervals; {}", intervals.len(), usage.join(", ")))
+}
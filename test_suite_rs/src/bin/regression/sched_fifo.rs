@@ -16,7 +16,10 @@ pub struct MyArgs {
 
     /// max running time
     #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
-    pub max_time: Option<u64>
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
 }
 
 pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
@@ -27,8 +30,8 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
     let cpus = num_cpus::get();
     let cgroup_expected_bw = cpus as f64 * args.runtime_ms as f64 / args.period_ms as f64;
     let fifo_expected_bw = cpus as f64 - cgroup_expected_bw;
-    let cgroup_error = cgroup_expected_bw * 0.025; // 2.5% error
-    let fifo_error = fifo_expected_bw * 0.025; // 2.5% error
+    let error = args.stats.tolerance.unwrap_or(0.01 * cpus as f64); // 1% of each cpu
+    let confidence = args.stats.confidence;
 
     let test_header =
         if is_batch_test() {
@@ -40,26 +43,15 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
     batch_test_header(test_header, "regression");
 
     let result = main(args, ctrlc_flag)
-        .and_then(|(fifo_bw, cgroup_bw)| {
-            if f64::abs(cgroup_bw - cgroup_expected_bw) >= cgroup_error {
-                return Err(format!("Expected cgroup tasks to use {:.2} units of total runtime, but used {:.2} units", cgroup_expected_bw, cgroup_bw).into());
-            }
-
-            if f64::abs(fifo_bw - fifo_expected_bw) >= fifo_error {
-                return Err(format!("Expected SCHED_FIFO tasks to use {:.2} units of total runtime, but used {:.2} units", fifo_expected_bw, fifo_bw).into());
-            }
-
-            Ok(format!("Cgroup processes got {:.2} units of total runtime, while SCHED_FIFO processes got {:.2} units of total runtime ", cgroup_bw, fifo_bw))
-        });
-
-    if is_batch_test() {
-        batch_test_result(result)
-    } else {
-        batch_test_result_details(result)
-    }
+        .and_then(|(fifo_samples, cgroup_samples)| Ok(vec![
+            check_samples("Cgroup processes", cgroup_samples, cgroup_expected_bw, error, confidence)?,
+            check_samples("SCHED_FIFO processes", fifo_samples, fifo_expected_bw, error, confidence)?,
+        ]));
+
+    batch_test_result_stat(result)
 }
 
-pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(Samples, Samples), Box<dyn std::error::Error>> {
     let cpus = num_cpus::get();
     let cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, false)?;
 
@@ -82,17 +74,21 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(f64, f64), Bo
             set_cpuset_to_pid(proc.id(), &CpuSet::single(cpu as u32)?)
         })?;
 
-    wait_loop(args.max_time, ctrlc_flag)?;
-
-    let mut fifo_total_usage = 0f64;
-    for proc in fifo_processes.iter() {
-        fifo_total_usage += get_process_total_cpu_usage(proc.id())?;
-    }
-
-    let mut cgroup_total_usage = 0f64;
-    for proc in cgroup_processes.iter() {
-        cgroup_total_usage += get_process_total_cpu_usage(proc.id())?;
-    }
+    let fifo_pids: Vec<_> = fifo_processes.iter().map(|proc| proc.id()).collect();
+    let cgroup_pids: Vec<_> = cgroup_processes.iter().map(|proc| proc.id()).collect();
+    let mut fifo_meter = BandwidthMeter::new(&fifo_pids)?;
+    let mut cgroup_meter = BandwidthMeter::new(&cgroup_pids)?;
+    let mut cgroup_samples = Vec::new();
+
+    let fifo_samples = collect_window_samples(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || {
+            cgroup_samples.push(cgroup_meter.sample()?);
+            fifo_meter.sample()
+        }
+    )?;
 
     fifo_processes.into_iter()
         .try_for_each(|mut proc| proc.kill())?;
@@ -102,5 +98,5 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(f64, f64), Bo
 
     cgroup.destroy()?;
 
-    Ok((fifo_total_usage, cgroup_total_usage))
+    Ok((fifo_samples, Samples::new(cgroup_samples)))
 }
\ No newline at end of file
@@ -1,6 +1,6 @@
 use hcbs_test_suite::prelude::*;
 use std::thread;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 
 #[derive(clap::Parser, Debug)]
 pub struct MyArgs {
@@ -23,22 +23,51 @@ pub struct MyArgs {
     /// max running time
     #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
     pub max_time: Option<u64>,
+
+    /// RNG's seed (random if not given)
+    #[arg(long = "seed", value_name = "u64")]
+    pub seed: Option<u64>,
 }
 
-pub fn batch_runner(args: MyArgs, rng: Option<&mut dyn rand::RngCore>, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+impl MyArgs {
+    pub fn to_args(&self) -> Vec<String> {
+        let mut out = vec![
+            "-c".to_owned(), self.cgroup.clone(),
+            "-r".to_owned(), self.runtime_min_ms.to_string(),
+            "-R".to_owned(), self.runtime_max_ms.to_string(),
+            "-p".to_owned(), self.period_ms.to_string(),
+        ];
+
+        if let Some(max_time) = self.max_time {
+            out.extend(["-t".to_owned(), max_time.to_string()]);
+        }
+
+        if let Some(seed) = self.seed {
+            out.extend(["--seed".to_owned(), seed.to_string()]);
+        }
+
+        out
+    }
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<bool, Box<dyn std::error::Error>> {
     if is_batch_test() && args.max_time.is_none() {
         Err(format!("Batch testing requires a maximum running time"))?;
     }
 
     batch_test_header(&format!("cgroup_make_destroy c{} r{} R{} p{}", args.cgroup, args.runtime_min_ms, args.runtime_max_ms, args.period_ms), "stress");
-    batch_test_result(main(args, rng, ctrlc_flag))?;
+    let result = main(args, ctrlc_flag);
+    let success = result.is_ok();
+    batch_test_result(result)?;
 
-    Ok(())
+    Ok(success)
 }
 
-pub fn main(args: MyArgs, rng: Option<&mut dyn rand::RngCore>, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
-    let mut thread_rng = rand::rng();
-    let rng = rng.unwrap_or_else(|| &mut thread_rng);
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rng: Box<dyn rand::RngCore> = match args.seed {
+        Some(seed) => Box::new(rand::rngs::StdRng::seed_from_u64(seed)),
+        None => Box::new(rand::rng()),
+    };
 
     wait_loop_periodic_fn(0f32, args.max_time, ctrlc_flag,
         || {
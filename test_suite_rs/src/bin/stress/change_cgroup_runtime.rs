@@ -27,7 +27,25 @@ pub struct MyArgs {
     pub max_time: Option<u64>,
 }
 
-pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+impl MyArgs {
+    pub fn to_args(&self) -> Vec<String> {
+        let mut out = vec![
+            "-c".to_owned(), self.cgroup.clone(),
+            "-r".to_owned(), self.runtime1_ms.to_string(),
+            "-R".to_owned(), self.runtime2_ms.to_string(),
+            "-p".to_owned(), self.period_ms.to_string(),
+            "-P".to_owned(), self.change_period.to_string(),
+        ];
+
+        if let Some(max_time) = self.max_time {
+            out.extend(["-t".to_owned(), max_time.to_string()]);
+        }
+
+        out
+    }
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<bool, Box<dyn std::error::Error>> {
     if is_batch_test() && args.max_time.is_none() {
         Err(format!("Batch testing requires a maximum running time"))?;
     }
@@ -42,9 +60,11 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
         };
 
     batch_test_header(&test_header, "stress");
-    batch_test_result(main(args, ctrlc_flag))?;
+    let result = main(args, ctrlc_flag);
+    let success = result.is_ok();
+    batch_test_result(result)?;
 
-    Ok(())
+    Ok(success)
 }
 
 pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
@@ -0,0 +1,224 @@
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::cgroup::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup's name
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    pub runtime_ms: u64,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    pub period_ms: u64,
+
+    /// number of processes to spawn
+    #[arg(short = 'n', long = "num-tasks", default_value = "2", value_name = "#num")]
+    pub num_tasks: u64,
+
+    /// hotplug change period, i.e. how long the cpus stay offline/online
+    #[arg(short = 'P', long = "change-period", value_name = "secs: f32")]
+    pub change_period: f32,
+
+    /// tasks' allowed cpus [default: all online cpus]
+    #[arg(long = "cpu-set", value_parser = <CpuSet as std::str::FromStr>::from_str)]
+    pub cpu_set: Option<CpuSet>,
+
+    /// cpus to take offline and online [default: all the hotpluggable cpus]
+    #[arg(long = "hotplug-set", value_parser = <CpuSet as std::str::FromStr>::from_str)]
+    pub hotplug_set: Option<CpuSet>,
+
+    /// max running time
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    pub max_time: Option<u64>,
+}
+
+impl MyArgs {
+    pub fn to_args(&self) -> Vec<String> {
+        let mut out = vec![
+            "-c".to_owned(), self.cgroup.clone(),
+            "-r".to_owned(), self.runtime_ms.to_string(),
+            "-p".to_owned(), self.period_ms.to_string(),
+            "-n".to_owned(), self.num_tasks.to_string(),
+            "-P".to_owned(), self.change_period.to_string(),
+        ];
+
+        if let Some(cpu_set) = &self.cpu_set {
+            out.extend(["--cpu-set".to_owned(), cpu_set.to_string()]);
+        }
+
+        if let Some(hotplug_set) = &self.hotplug_set {
+            out.extend(["--hotplug-set".to_owned(), hotplug_set.to_string()]);
+        }
+
+        if let Some(max_time) = self.max_time {
+            out.extend(["-t".to_owned(), max_time.to_string()]);
+        }
+
+        out
+    }
+}
+
+// Brings the hotplugged cpus back online, also when the test fails.
+struct HotplugGuard {
+    cpus: CpuSet,
+}
+
+impl HotplugGuard {
+    fn set_online(&self, online: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.cpus.iter()
+            .try_for_each(|cpu| set_cpu_online(cpu, online))
+    }
+}
+
+impl Drop for HotplugGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.set_online(true) {
+            eprintln!("Error in restoring cpus {} online: {err}", self.cpus);
+        }
+    }
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<bool, Box<dyn std::error::Error>> {
+    if is_batch_test() && args.max_time.is_none() {
+        Err("Batch testing requires a maximum running time")?;
+    }
+
+    let test_header = format!("cpu_hotplug c{} r{} p{} n{} P{:.2} set{:?} hotplug{:?}",
+        args.cgroup, args.runtime_ms, args.period_ms, args.num_tasks, args.change_period,
+        args.cpu_set, args.hotplug_set);
+    let test_header =
+        if is_batch_test() {
+            test_header
+        } else {
+            test_header + " (Ctrl+C to stop)"
+        };
+
+    batch_test_header(&test_header, "stress");
+    let result = main(args, ctrlc_flag);
+    let success = result.is_ok();
+    batch_test_result_skippable(result)?;
+
+    Ok(success)
+}
+
+// The cgroup must keep its configured runtime/period, and every online cpu
+// must give the configured runtime to the cgroup's runqueue.
+fn check_bandwidth(cgroup: &str, runtime_us: u64, period_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let (cur_runtime_us, cur_period_us) = (get_cgroup_runtime_us(cgroup)?, get_cgroup_period_us(cgroup)?);
+    if (cur_runtime_us, cur_period_us) != (runtime_us, period_us) {
+        Err(format!("cgroup {cgroup} has runtime/period {cur_runtime_us}/{cur_period_us} us, expected {runtime_us}/{period_us} us"))?;
+    }
+
+    let sched_debug = SchedDebug::snapshot()?;
+    let rt_rqs = sched_debug.rt_rqs(cgroup);
+    for cpu in CpuSet::all()?.iter() {
+        let rt_runtime_ms = rt_rqs.iter()
+            .find(|rt_rq| rt_rq.cpu == cpu)
+            .ok_or_else(|| format!("cgroup {cgroup} has no rt_rq on cpu {cpu}"))?
+            .rt_runtime_ms()
+            .ok_or_else(|| format!("cgroup {cgroup} has no rt_runtime on cpu {cpu}"))?;
+
+        if (rt_runtime_ms - runtime_us as f64 / 1000f64).abs() > 1e-3 {
+            Err(format!("cgroup {cgroup} has runtime {rt_runtime_ms} ms on cpu {cpu}, expected {} ms",
+                runtime_us as f64 / 1000f64))?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<Skippable<(), Box<dyn std::error::Error>>, Box<dyn std::error::Error>> {
+    let hotplug_set = match args.hotplug_set {
+        Some(hotplug_set) => hotplug_set,
+        None => get_hotpluggable_cpus()?,
+    };
+
+    if hotplug_set.num_cpus() == 0 {
+        return Ok(Skippable::Skipped("No hotpluggable cpus".into()));
+    }
+
+    if hotplug_set.complement()?.num_cpus() == 0 {
+        Err("At least one cpu must stay online")?;
+    }
+
+    // the cgroup's runtime on each cpu is checked through sched/debug
+    if let Err(err) = SchedDebug::snapshot() {
+        return Ok(Skippable::Skipped(format!("Cannot read sched/debug: {err}").into()));
+    }
+
+    let cpu_set = match args.cpu_set {
+        Some(cpu_set) => cpu_set,
+        None => CpuSet::all()?,
+    };
+
+    let (runtime_us, period_us) = (args.runtime_ms * 1000, args.period_ms * 1000);
+    let cgroup = MyCgroup::new(&args.cgroup, runtime_us, period_us, true)?;
+    migrate_task_to_cgroup(&args.cgroup, std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    let procs: Vec<_> = (0..args.num_tasks)
+        .map(|_| run_yes()).try_collect()?;
+
+    let pids: Vec<_> = procs.iter().map(|proc| proc.id()).collect();
+    pids.iter()
+        .try_for_each(|pid| {
+            migrate_task_to_cgroup(&args.cgroup, *pid)?;
+            set_scheduler(*pid, SchedPolicy::RR(50))?;
+            set_cpuset_to_pid(*pid, &cpu_set)?;
+
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
+
+    let hotplug = HotplugGuard { cpus: hotplug_set };
+    let mut offline = false;
+    let mut cycle = 0u64;
+    let mut last_runtimes: Vec<_> = pids.iter()
+        .map(|pid| get_process_runtime_ns(*pid)).try_collect()?;
+
+    let update_fn = || {
+        // every task must have run while the cpus were in the previous state
+        let runtimes: Vec<_> = pids.iter()
+            .map(|pid| get_process_runtime_ns(*pid)).try_collect()?;
+
+        if let Some((pid, _)) = pids.iter().zip(runtimes.iter().zip(last_runtimes.iter()))
+            .find(|(_, (runtime, last_runtime))| runtime <= last_runtime)
+        {
+            Err(format!("Task {pid} made no progress while cpus {} were {} (cycle {cycle})",
+                hotplug.cpus, if offline { "offline" } else { "online" }))?;
+        }
+
+        last_runtimes = runtimes;
+        offline = !offline;
+        hotplug.set_online(!offline)?;
+
+        if !offline {
+            cycle += 1;
+
+            // offlining all the allowed cpus of a task breaks its affinity
+            pids.iter()
+                .try_for_each(|pid| set_cpuset_to_pid(*pid, &cpu_set))?;
+
+            check_bandwidth(&args.cgroup, runtime_us, period_us)
+                .map_err(|err| format!("After cycle {cycle}: {err}"))?;
+        }
+
+        Ok(())
+    };
+
+    wait_loop_periodic_fn(args.change_period, args.max_time, ctrlc_flag, update_fn)?;
+
+    drop(hotplug);
+    check_bandwidth(&args.cgroup, runtime_us, period_us)?;
+
+    procs.into_iter()
+        .try_for_each(|mut proc| proc.kill())?;
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+    migrate_task_to_cgroup(".", std::process::id())?;
+    cgroup.destroy()?;
+
+    Ok(Skippable::Result(()))
+}
@@ -4,10 +4,14 @@ mod cgroup_make_destroy;
 mod change_cgroup_runtime;
 mod change_pinning;
 mod change_priority;
+mod cpu_hotplug;
 mod migrate;
+mod replay;
 mod run_all;
 mod switch_class;
 
+use hcbs_test_suite::prelude::ExitFlag;
+
 #[derive(clap::Parser, Debug)]
 #[command(about, long_about = None)]
 pub enum Command {
@@ -16,9 +20,19 @@ pub enum Command {
     /// This command runs all the listed tests. It generates pseudo-random
     /// parameters for each of these tests and chooses which to run at random,
     /// totalling to a user specified amount of tests.
+    /// 
+    /// The concrete arguments and the outcome of every run test are written
+    /// to a replay file, which can be fed to the replay command.
     #[command(name = "all", verbatim_doc_comment)]
     All(run_all::MyArgs),
 
+    /// Replay tests recorded by the all command
+    /// 
+    /// This command re-runs the tests recorded in a replay file. By default
+    /// only the failed (or interrupted) tests are run.
+    #[command(name = "replay", verbatim_doc_comment)]
+    Replay(replay::MyArgs),
+
     /// Stress test on cgroup creation and destruction
     /// 
     /// This test creates and destroys a single cgroup at high rate, with the
@@ -76,6 +90,54 @@ pub enum Command {
     /// Constraints: runtime <= period
     #[command(name = "task-sched-class", verbatim_doc_comment)]
     SwitchClass(switch_class::MyArgs),
+
+    /// Stress test on CPU hotplug
+    /// 
+    /// This test runs tasks in a RT cgroup while periodically taking CPUs
+    /// offline and online again, both in and out of the tasks' affinity. After
+    /// every state change the tasks must have made progress, and after each
+    /// cycle the cgroup's runtime must be back to its configured value on
+    /// every online CPU.
+    /// 
+    /// Constraints: runtime <= period; at least one CPU must stay online
+    #[command(name = "cpu-hotplug", verbatim_doc_comment)]
+    CpuHotplug(cpu_hotplug::MyArgs),
+}
+
+impl Command {
+    /// Command line arguments of the stress binary that reproduce this test.
+    pub fn to_args(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        use Command::*;
+
+        let (name, args) = match self {
+            All(_) | Replay(_) => { return Err("Only single tests can be recorded".into()); },
+            CgroupMakeDestroy(args) => ("cgroup-setup", args.to_args()),
+            ChangeCgroupRuntime(args) => ("cgroup-runtime", args.to_args()),
+            ChangePinning(args) => ("task-pinning", args.to_args()),
+            ChangePriority(args) => ("task-priority", args.to_args()),
+            Migrate(args) => ("task-migration", args.to_args()),
+            SwitchClass(args) => ("task-sched-class", args.to_args()),
+            CpuHotplug(args) => ("cpu-hotplug", args.to_args()),
+        };
+
+        Ok(std::iter::once(name.to_owned()).chain(args).collect())
+    }
+
+    /// Run a single test, returning whether it succeeded.
+    pub fn run(self, ctrlc_flag: Option<ExitFlag>) -> Result<bool, Box<dyn std::error::Error>> {
+        use Command::*;
+
+        match self {
+            All(_) | Replay(_) => Err("Only single tests can be run".into()),
+            CgroupMakeDestroy(args) => cgroup_make_destroy::batch_runner(args, ctrlc_flag),
+            ChangeCgroupRuntime(args) => change_cgroup_runtime::batch_runner(args, ctrlc_flag),
+            ChangePinning(args) => change_pinning::batch_runner(args, ctrlc_flag),
+            ChangePriority(args) => change_priority::batch_runner(args, ctrlc_flag),
+            Migrate(args) => migrate::batch_runner(args, ctrlc_flag),
+            SwitchClass(args) => switch_class::batch_runner(args, ctrlc_flag),
+            CpuHotplug(args) => cpu_hotplug::batch_runner(args, ctrlc_flag),
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -85,12 +147,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args {
         All(args) => run_all::main(args, None),
-        CgroupMakeDestroy(args) => cgroup_make_destroy::batch_runner(args, None, None),
-        ChangeCgroupRuntime(args) => change_cgroup_runtime::batch_runner(args, None),
-        ChangePinning(args) => change_pinning::batch_runner(args, None),
-        ChangePriority(args) => change_priority::batch_runner(args, None),
-        Migrate(args) => migrate::batch_runner(args, None),
-        SwitchClass(args) => switch_class::batch_runner(args, None),
+        Replay(args) => replay::main(args, None),
+        test => test.run(None).map(|_| ()),
     }
 }
-
@@ -0,0 +1,190 @@
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// replay file written by the all command
+    #[arg(value_name = "path")]
+    pub file: String,
+
+    /// replay only the given test number
+    #[arg(long = "only", value_name = "N", conflicts_with = "all")]
+    pub only: Option<u64>,
+
+    /// replay all the recorded tests, not just the failed ones
+    #[arg(long = "all")]
+    pub all: bool,
+}
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+pub enum Outcome {
+    Running,
+    Success,
+    Failure,
+}
+
+impl std::fmt::Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Outcome::Running => write!(f, "running"),
+            Outcome::Success => write!(f, "success"),
+            Outcome::Failure => write!(f, "failure"),
+        }
+    }
+}
+
+impl std::str::FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "running" => Ok(Outcome::Running),
+            "success" => Ok(Outcome::Success),
+            "failure" => Ok(Outcome::Failure),
+            _ => Err(format!("Unknown test outcome {s}")),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayEntry {
+    pub index: u64,
+    pub outcome: Outcome,
+    pub args: Vec<String>,
+}
+
+/// Record of the sub-tests run by the all command.
+///
+/// Each line holds the test number, its outcome and the command line arguments
+/// of the stress binary that reproduce it, e.g.:
+/// `37 failure task-migration -c g0 -r 60 -p 120 -P 1.25 -t 60`.
+/// A test is marked as running before it starts, so that a crashed run still
+/// records the culprit. Lines starting with '#' are comments.
+pub struct ReplayFile {
+    path: String,
+    header: String,
+    entries: Vec<ReplayEntry>,
+}
+
+impl ReplayFile {
+    pub fn new(path: &str, header: &str) -> Self {
+        Self {
+            path: path.to_owned(),
+            header: header.to_owned(),
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn read(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read replay file {path}: {err}"))?;
+
+        let mut header = String::new();
+        let mut entries = Vec::new();
+        for (num, line) in data.lines().enumerate() {
+            let line = line.trim_ascii();
+            if line.is_empty() {
+                continue;
+            }
+
+            if let Some(comment) = line.strip_prefix('#') {
+                if !header.is_empty() {
+                    header.push('\n');
+                }
+
+                header += comment.trim_ascii();
+                continue;
+            }
+
+            let mut fields = line.split_ascii_whitespace();
+            let index = fields.next()
+                .ok_or_else(|| format!("Replay file {path}, line {}: missing test number", num + 1))?
+                .parse::<u64>()
+                .map_err(|err| format!("Replay file {path}, line {}: {err}", num + 1))?;
+            let outcome = fields.next()
+                .ok_or_else(|| format!("Replay file {path}, line {}: missing test outcome", num + 1))?
+                .parse::<Outcome>()
+                .map_err(|err| format!("Replay file {path}, line {}: {err}", num + 1))?;
+
+            entries.push(ReplayEntry {
+                index,
+                outcome,
+                args: fields.map(|field| field.to_owned()).collect(),
+            });
+        }
+
+        Ok(Self { path: path.to_owned(), header, entries })
+    }
+
+    fn write(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut data: String = self.header.lines()
+            .map(|line| format!("# {line}\n"))
+            .collect();
+        for entry in self.entries.iter() {
+            data += &format!("{} {} {}\n", entry.index, entry.outcome, entry.args.join(" "));
+        }
+
+        std::fs::write(&self.path, data)
+            .map_err(|err| format!("Error in writing replay file {}: {err}", self.path))?;
+
+        Ok(())
+    }
+
+    pub fn start_test(&mut self, index: u64, args: Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        self.entries.push(ReplayEntry { index, outcome: Outcome::Running, args });
+        self.write()
+    }
+
+    pub fn end_test(&mut self, success: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let entry = self.entries.last_mut()
+            .ok_or("No test has been started")?;
+
+        entry.outcome = if success { Outcome::Success } else { Outcome::Failure };
+        self.write()
+    }
+}
+
+pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    unsafe { set_batch_test(); }
+
+    let ctrlc_flag = match ctrlc_flag {
+        Some(exit) => exit,
+        None => create_ctrlc_handler()?,
+    };
+
+    let replay = ReplayFile::read(&args.file)?;
+    let entries: Vec<_> = replay.entries.iter()
+        .filter(|entry| match args.only {
+            Some(only) => entry.index == only,
+            None => args.all || entry.outcome != Outcome::Success,
+        })
+        .collect();
+
+    if entries.is_empty() {
+        match args.only {
+            Some(only) => Err(format!("Test {only} not found in replay file {}", args.file))?,
+            None => println!("[stress] No tests to replay in {}", args.file),
+        }
+    }
+
+    let mut failures = 0u64;
+    for entry in entries.iter() {
+        if ctrlc_flag.is_exit() {
+            break;
+        }
+
+        let command = <crate::Command as clap::Parser>::try_parse_from(
+            std::iter::once("stress").chain(entry.args.iter().map(|arg| arg.as_str()))
+        ).map_err(|err| format!("Test {}: invalid recorded arguments: {err}", entry.index))?;
+
+        println!("[stress] Replaying test {} (recorded {})", entry.index, entry.outcome);
+        if !command.run(Some(ctrlc_flag.clone()))? {
+            failures += 1;
+        }
+    }
+
+    println!("[stress] Replay outcome: {}/{} failures/tests", failures, entries.len());
+
+    Ok(())
+}
@@ -1,6 +1,9 @@
 use hcbs_test_suite::prelude::*;
 use rand::*;
 
+use crate::Command;
+use crate::replay::ReplayFile;
+
 #[derive(clap::Parser, Debug)]
 pub struct MyArgs {
     /// cgroup's name
@@ -18,6 +21,10 @@ pub struct MyArgs {
     /// RNG's seed
     #[arg(long = "seed", value_name = "u64", default_value = "42")]
     pub seed: u64,
+
+    /// replay file, recording the arguments and outcome of each test
+    #[arg(short = 'o', long = "replay-file", value_name = "path", default_value = "stress_replay.txt")]
+    pub replay_file: String,
 }
 
 #[derive(Debug)]
@@ -52,8 +59,12 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn st
         None => create_ctrlc_handler()?,
     };
 
+    let mut replay = ReplayFile::new(&args.replay_file,
+        &format!("stress all -c {} -n {} -t {} --seed {}",
+            args.cgroup, args.num_tests, args.max_time_per_test, args.seed));
+
     let mut rand = rand::rngs::StdRng::seed_from_u64(args.seed);
-    for _ in 0..args.num_tests {
+    for test_num in 1..=args.num_tests {
         if ctrlc_flag.is_exit() {
             break;
         }
@@ -64,26 +75,25 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn st
         let runtime_min_ms = 20;
         let runtime_half_ms = (runtime_max_ms + runtime_min_ms) / 2;
 
-        match test_type {
+        let command = match test_type {
             TestType::CgroupMakeDestroy => {
                 let _runtime_min_ms = rand.random_range(runtime_min_ms..=runtime_half_ms);
                 let _runtime_max_ms = rand.random_range(runtime_half_ms..=runtime_max_ms);
 
-                crate::cgroup_make_destroy::batch_runner(crate::cgroup_make_destroy::MyArgs {
+                Command::CgroupMakeDestroy(crate::cgroup_make_destroy::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime_min_ms: _runtime_min_ms,
                     runtime_max_ms: _runtime_max_ms,
                     period_ms,
                     max_time: Some(args.max_time_per_test),
-                },  Some(&mut rand),
-                    Some(ctrlc_flag.clone()),
-                )?
+                    seed: Some(rand.random()),
+                })
             },
             TestType::ChangePinning => {
                 let runtime_ms = rand.random_range(runtime_min_ms..runtime_max_ms);
                 let change_period = rand.random_range(0.5f32..=3f32);
 
-                crate::change_pinning::batch_runner(crate::change_pinning::MyArgs {
+                Command::ChangePinning(crate::change_pinning::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime_ms,
                     period_ms,
@@ -91,68 +101,66 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn st
                     cpu_set1: "0,2".parse()?,
                     cpu_set2: "1,3".parse()?,
                     max_time: Some(args.max_time_per_test),
-                },  Some(ctrlc_flag.clone()),
-                )?
+                })
             },
             TestType::ChangePriority => {
                 let runtime_ms = rand.random_range(runtime_min_ms..runtime_max_ms);
                 let change_period = rand.random_range(0.5f32..=3f32);
 
-                crate::change_priority::batch_runner(crate::change_priority::MyArgs {
+                Command::ChangePriority(crate::change_priority::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime_ms,
                     period_ms,
                     change_period,
                     max_time: Some(args.max_time_per_test),
-                },  Some(ctrlc_flag.clone()),
-                )?
+                })
             },
             TestType::ChangeCgroupRuntime => {
                 let runtime1_ms = rand.random_range(runtime_min_ms..=runtime_half_ms);
                 let runtime2_ms = rand.random_range(runtime_half_ms..=runtime_max_ms);
                 let change_period = rand.random_range(0.5f32..=3f32);
 
-                crate::change_cgroup_runtime::batch_runner(crate::change_cgroup_runtime::MyArgs {
+                Command::ChangeCgroupRuntime(crate::change_cgroup_runtime::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime1_ms,
                     runtime2_ms,
                     period_ms,
                     change_period,
                     max_time: Some(args.max_time_per_test),
-                },  Some(ctrlc_flag.clone()),
-                )?
-                
+                })
             },
             TestType::Migrate => {
                 let runtime_ms = rand.random_range(runtime_min_ms..runtime_max_ms);
                 let change_period = rand.random_range(0.5f32..=3f32);
 
-                crate::migrate::batch_runner(crate::migrate::MyArgs {
+                Command::Migrate(crate::migrate::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime_ms,
                     period_ms,
                     change_period,
                     max_time: Some(args.max_time_per_test),
-                },  Some(ctrlc_flag.clone()),
-                )?
-                
+                })
             },
             TestType::SwitchClass => {
                 let runtime_ms = rand.random_range(runtime_min_ms..runtime_max_ms);
                 let change_period = rand.random_range(0.5f32..=3f32);
 
-                crate::switch_class::batch_runner(crate::switch_class::MyArgs {
+                Command::SwitchClass(crate::switch_class::MyArgs {
                     cgroup: args.cgroup.clone(),
                     runtime_ms,
                     period_ms,
                     change_period,
                     max_time: Some(args.max_time_per_test),
-                },  Some(ctrlc_flag.clone()),
-                )?
-
+                })
             },
-        }
+        };
+
+        replay.start_test(test_num, command.to_args()?)?;
+        let success = command.run(Some(ctrlc_flag.clone()))?;
+        replay.end_test(success)?;
     }
 
+    println!("[stress] Replay file written to {}", args.replay_file);
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -1,6 +1,8 @@
 #![feature(iterator_try_collect)]
 
 mod many_tasks;
+mod nested;
+mod round_robin;
 
 #[derive(clap::Parser, Debug)]
 #[command(about, long_about = None)]
@@ -16,6 +18,32 @@ pub enum Command {
     /// Constraints: runtime <= period
     #[command(name = "many", verbatim_doc_comment)]
     ManyTasks(many_tasks::MyArgs),
+
+    /// Run yes tasks in the leaves of a RT cgroup hierarchy
+    ///
+    /// This command builds a hierarchy of RT cgroups, e.g. a parent with 50%
+    /// of bandwidth and two children with 20% and 25%, and runs enough yes
+    /// tasks in each leaf to use its whole reservation, overloading the first
+    /// one. The test is successful if every leaf consumes the bandwidth
+    /// allocated to it: the parents' unused bandwidth is not leaked to the
+    /// leaves, and the overloaded leaf does not steal its siblings'.
+    ///
+    /// Constraints: children's bandwidth <= parent's bandwidth
+    #[command(name = "nested", verbatim_doc_comment)]
+    Nested(nested::MyArgs),
+
+    /// Run equal priority SCHED_RR yes tasks in a RT cgroup
+    ///
+    /// This command runs a number of SCHED_RR yes tasks with the same priority
+    /// on a single cpu in a RT cgroup, for every combination of the given
+    /// sched_rr_timeslice_ms values and cgroup reservations. The test is
+    /// successful if the tasks share the cgroup's bandwidth equally, i.e.
+    /// throttling neither breaks the timeslice accounting nor starves any of
+    /// them. The original timeslice is restored at the end.
+    ///
+    /// Constraints: runtime <= period
+    #[command(name = "rr", verbatim_doc_comment)]
+    RoundRobin(round_robin::MyArgs),
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -25,6 +53,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     match args {
         ManyTasks(args) => { many_tasks::batch_runner(args, None)?; },
+        Nested(args) => { nested::batch_runner(args, None)?; },
+        RoundRobin(args) => { round_robin::batch_runner(args, None)?; },
     };
 
     Ok(())
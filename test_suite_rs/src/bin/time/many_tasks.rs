@@ -1,6 +1,6 @@
 use hcbs_test_suite::prelude::*;
 
-#[derive(clap::Parser, Debug)]
+#[derive(clap::Parser, Debug, Clone)]
 pub struct MyArgs {
     /// cgroup's name
     #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
@@ -18,13 +18,56 @@ pub struct MyArgs {
     #[arg(short = 'n', long = "num-tasks", default_value= "1", value_name = "#num")]
     pub num_tasks: u64,
 
+    /// pin one process on each allowed cpu, and check the bandwidth of every
+    /// cpu separately
+    #[arg(long = "per-cpu", conflicts_with = "num_tasks")]
+    pub per_cpu: bool,
+
     /// task's allowed cpus
-    #[arg(long = "cpu-set", value_parser = <CpuSetUnchecked as std::str::FromStr>::from_str)]
+    #[arg(long = "cpu-set", value_parser = <CpuSetUnchecked as std::str::FromStr>::from_str, conflicts_with = "num_cpus")]
     pub cpu_set: Option<CpuSetUnchecked>,
 
+    /// number of allowed cpus, chosen by the placement policy
+    #[arg(long = "cpus", value_name = "u64")]
+    pub num_cpus: Option<u64>,
+
+    /// how to choose the allowed cpus: first, one-per-core, same-package,
+    /// numa:<node>
+    #[arg(long = "placement", value_name = "policy", default_value = "first", requires = "num_cpus")]
+    pub placement: CpuPlacement,
+
     /// max running time
     #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
     pub max_time: Option<u64>,
+
+    /// trace the tasks and verify the budget enforcement in every period
+    #[arg(long = "trace")]
+    pub trace: bool,
+
+    /// accepted budget overrun per period, when tracing
+    #[arg(long = "trace-epsilon", value_name = "us: u64", default_value = "500", requires = "trace")]
+    pub trace_epsilon_us: u64,
+
+    /// scheduler feature required by the test, e.g. HRTICK_DL or NO_HRTICK_DL
+    #[arg(long = "sched-feature", value_name = "feature")]
+    pub sched_features: Vec<SchedFeature>,
+
+    /// run the test with the scheduler feature both enabled and disabled,
+    /// e.g. HRTICK_DL
+    #[arg(long = "feature-matrix", value_name = "feature", value_parser = parse_matrix_feature)]
+    pub feature_matrix: Vec<String>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
+}
+
+// the matrix tests both states of a feature, so only its name is accepted
+fn parse_matrix_feature(s: &str) -> Result<String, String> {
+    match s.parse::<SchedFeature>()? {
+        SchedFeature { name, enabled: true } => Ok(name),
+        SchedFeature { name, enabled: false } =>
+            Err(format!("Expected a feature name without the NO_ prefix, e.g. {name}: both states are tested")),
+    }
 }
 
 pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
@@ -32,16 +75,75 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
         Err(format!("Batch testing requires a maximum running time"))?;
     }
 
+    if let Some(feature) = args.sched_features.iter()
+        .find(|feature| args.feature_matrix.contains(&feature.name))
+    {
+        Err(format!("Scheduler feature {} is both required and in the matrix", feature.name))?;
+    }
+
+    // the handler can be registered only once, so it is shared by all the
+    // combinations
+    let ctrlc_flag = match ctrlc_flag {
+        Some(flag) => flag,
+        None => create_ctrlc_handler()?,
+    };
+
+    for combination in sched_feature_matrix(&args.feature_matrix) {
+        if ctrlc_flag.is_exit() {
+            break;
+        }
+
+        let features: Vec<_> = args.sched_features.iter().cloned()
+            .chain(combination)
+            .collect();
+
+        run_with_features(args.clone(), features, Some(ctrlc_flag.clone()))?;
+    }
+
+    Ok(())
+}
+
+fn run_with_features(args: MyArgs, features: Vec<SchedFeature>, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
     let single_bw = args.runtime_ms as f64 / args.period_ms as f64;
-    let num_cpus = args.cpu_set.as_ref()
-        .map_or(CpuSet::all()?.num_cpus(), |cpu_set| cpu_set.num_cpus());
+    let expected_bw =
+        if args.per_cpu {
+            // a single cpu cannot receive more than the cgroup's runtime
+            single_bw
+        } else {
+            let num_cpus = match (&args.cpu_set, args.num_cpus) {
+                (Some(cpu_set), _) => cpu_set.num_cpus(),
+                (None, Some(num_cpus)) => num_cpus as usize,
+                (None, None) => CpuSet::all()?.num_cpus(),
+            };
 
-    let total_cgroup_bw = single_bw * num_cpus as f64;
-    let max_expected_bw = f64::min(total_cgroup_bw, args.num_tasks as f64);
-    let max_error = 0.01;
+            // the processes cannot use more than one cpu each
+            f64::min(single_bw * num_cpus as f64, args.num_tasks as f64)
+        };
+    let max_error = args.stats.tolerance.unwrap_or(0.01);
+    let confidence = args.stats.confidence;
 
-    let test_header = format!("time c{} n{} r{} p{} set{:?}",
-        args.cgroup, args.num_tasks, args.runtime_ms, args.period_ms, args.cpu_set);
+    let test_header =
+        if args.per_cpu {
+            format!("time c{} per-cpu r{} p{} set{:?}",
+                args.cgroup, args.runtime_ms, args.period_ms, args.cpu_set)
+        } else {
+            format!("time c{} n{} r{} p{} set{:?}",
+                args.cgroup, args.num_tasks, args.runtime_ms, args.period_ms, args.cpu_set)
+        };
+    let test_header = match args.num_cpus {
+        Some(num_cpus) => format!("{test_header} cpus{num_cpus} {}", args.placement),
+        None => test_header,
+    };
+    let test_header =
+        if features.is_empty() {
+            test_header
+        } else {
+            let features: Vec<_> = features.iter()
+                .map(|feature| feature.to_string())
+                .collect();
+
+            format!("{test_header} feat[{}]", features.join(","))
+        };
     let test_header =
         if is_batch_test() {
             test_header
@@ -51,66 +153,133 @@ pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Bo
 
     batch_test_header(&test_header, "time");
 
-    let result = main(args, ctrlc_flag)
-        .and_then(|used_bw| { 
-            match used_bw {
-                Skippable::Result(used_bw) => 
-                    if f64::abs(used_bw - max_expected_bw) < max_error {
-                        Ok(Skippable::Result(format!("Processes used an average of {used_bw:.5} units of CPU bandwidth.")))
-                    } else {
-                        Err(format!("Expected cgroup's task to use {:.2} units of runtime, but used {:.2}", max_expected_bw, used_bw).into())
-                    },
-                Skippable::Skipped(err) => Ok(Skippable::Skipped(err)),
-            }
-        });
-
-    if is_batch_test() {
-        batch_test_result_skippable(result)
-    } else {
-        batch_test_result_skippable_details(result)
+    match main(args, &features, ctrlc_flag) {
+        Ok(Skippable::Skipped(err)) =>
+            batch_test_result_skippable::<()>(Ok(Skippable::Skipped(err))),
+        Ok(Skippable::Result(samples)) =>
+            batch_test_result_stat(
+                samples.into_iter()
+                    .map(|(cpu, samples)| match cpu {
+                        Some(cpu) => check_samples(&format!("CPU {cpu}"), samples, expected_bw, max_error, confidence),
+                        None => check_samples("Processes", samples, expected_bw, max_error, confidence),
+                    })
+                    .try_collect()
+            ),
+        Err(err) =>
+            batch_test_result_stat(Err(err)),
     }
 }
 
-pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<Skippable<f64, Box<dyn std::error::Error>>, Box<dyn std::error::Error>> {
+/// Bandwidth samples of all the processes (no cpu), or of each pinned cpu.
+pub type CpuSamples = Vec<(Option<u32>, Samples)>;
+
+pub fn main(args: MyArgs, features: &[SchedFeature], ctrlc_flag: Option<ExitFlag>) -> Result<Skippable<CpuSamples, Box<dyn std::error::Error>>, Box<dyn std::error::Error>> {
     // check if the cpu_set is valid
-    let cpu_set = args.cpu_set
-        .map(|cpu_set| Into::<Result<CpuSet, CpuSetBuildError>>::into(cpu_set))
-        .transpose();
+    let cpu_set = match (args.cpu_set, args.num_cpus) {
+        (Some(cpu_set), _) => Into::<Result<CpuSet, CpuSetBuildError>>::into(cpu_set).map(Some),
+        (None, Some(num_cpus)) => args.placement.cpu_set(num_cpus).map(Some),
+        (None, None) => Ok(None),
+    };
 
     let cpu_set =
         match cpu_set {
-            Err(err @ CpuSetBuildError::UnavailableCPU(_)) =>
+            Err(err @ (CpuSetBuildError::UnavailableCPU(_) |
+                       CpuSetBuildError::UnavailableCPUs |
+                       CpuSetBuildError::UnavailableNode(_))) =>
                 { return Ok(Skippable::Skipped(err.into())); },
             Ok(cpu_set) => cpu_set,
             Err(err) =>
                 { return Err(err.into()); },
         };
 
+    // the original features are restored when the guard is dropped, also on
+    // errors; a kernel without the requested features skips the test
+    let features_guard =
+        if features.is_empty() {
+            None
+        } else {
+            let guard = SchedFeaturesGuard::new()
+                .and_then(|mut guard| { guard.apply(features)?; Ok(guard) });
+
+            match guard {
+                Ok(guard) => Some(guard),
+                Err(err) =>
+                    { return Ok(Skippable::Skipped(format!("Cannot set scheduler features: {err}").into())); },
+            }
+        };
+
     // run the tasks
     let cgroup = MyCgroup::new(&args.cgroup, args.runtime_ms * 1000, args.period_ms * 1000, true)?;
 
     migrate_task_to_cgroup(&args.cgroup, std::process::id())?;
 
-    let procs: Vec<_> = (0..args.num_tasks)
+    let allowed_cpus = match &cpu_set {
+        Some(cpu_set) => cpu_set.clone(),
+        None => CpuSet::all()?,
+    };
+
+    let tracer =
+        if args.trace {
+            Some(Tracer::start(&allowed_cpus, 16384)?)
+        } else {
+            None
+        };
+
+    // the cpu each process is pinned to, if any
+    let pinned_cpus: Vec<_> =
+        if args.per_cpu {
+            allowed_cpus.iter().map(Some).collect()
+        } else {
+            vec![None; args.num_tasks as usize]
+        };
+
+    let procs: Vec<_> = pinned_cpus.iter()
         .map(|_| run_yes()).try_collect()?;
 
     set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
-    procs.iter()
-        .try_for_each(|proc| {
+    procs.iter().zip(pinned_cpus.iter())
+        .try_for_each(|(proc, cpu)| {
             migrate_task_to_cgroup(&args.cgroup, proc.id())?;
             set_scheduler(proc.id(), SchedPolicy::RR(50))?;
-            if cpu_set.is_some() {
-                set_cpuset_to_pid(proc.id(), cpu_set.as_ref().unwrap())?;
+            if let Some(cpu) = cpu {
+                set_cpuset_to_pid(proc.id(), &CpuSet::single(*cpu)?)?;
+            } else if let Some(cpu_set) = &cpu_set {
+                set_cpuset_to_pid(proc.id(), cpu_set)?;
             }
 
             Ok::<_, Box<dyn std::error::Error>>(())
         })?;
 
-    wait_loop(args.max_time, ctrlc_flag)?;
+    let pids: Vec<_> = procs.iter().map(|proc| proc.id()).collect();
 
-    let total_usage =
-        procs.iter()
-            .try_fold(0f64, |sum, proc| Ok::<f64, String>(sum + get_process_total_cpu_usage(proc.id())?))?;
+    // one meter for all the processes, or one per pinned process
+    let (cpus, mut meters): (Vec<_>, Vec<_>) =
+        if args.per_cpu {
+            let meters: Vec<_> = pids.iter()
+                .map(|pid| BandwidthMeter::new(&[*pid]))
+                .try_collect()?;
+
+            (pinned_cpus, meters)
+        } else {
+            (vec![None], vec![BandwidthMeter::new(&pids)?])
+        };
+
+    let samples = collect_window_samples_multi(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || meters.iter_mut()
+            .map(|meter| meter.sample())
+            .try_collect()
+    )?;
+
+    let violations = tracer
+        .map(|tracer| tracer.stop())
+        .transpose()?
+        .map(|events| {
+            let intervals = execution_intervals(&events, &pids);
+            verify_budget(&intervals, args.runtime_ms * 1_000_000, args.period_ms * 1_000_000, args.trace_epsilon_us * 1000)
+        });
 
     procs.into_iter()
         .try_for_each(|mut proc| proc.kill())?;
@@ -118,6 +287,19 @@ pub fn main(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<Skippable<f64,
     set_scheduler(std::process::id(), SchedPolicy::other())?;
     migrate_task_to_cgroup(".", std::process::id())?;
     cgroup.destroy()?;
+    features_guard
+        .map(|guard| guard.restore())
+        .transpose()?;
+
+    if let Some(violations) = violations && !violations.is_empty() {
+        let shown: Vec<_> = violations.iter().take(5)
+            .map(|violation| violation.to_string())
+            .collect();
+
+        Err(format!("Budget of {} us exceeded in {} periods: {}{}",
+            args.runtime_ms * 1000, violations.len(), shown.join("; "),
+            if violations.len() > shown.len() { "; ..." } else { "" }))?;
+    }
 
-    Ok(Skippable::Result(total_usage))
+    Ok(Skippable::Result(cpus.into_iter().zip(samples).collect()))
 }
\ No newline at end of file
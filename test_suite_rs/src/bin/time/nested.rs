@@ -0,0 +1,186 @@
+use hcbs_test_suite::prelude::*;
+
+/// A cgroup of the hierarchy, written as `name:runtime/period` (in ms).
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct CgroupSpec {
+    pub name: String,
+    pub runtime_ms: u64,
+    pub period_ms: u64,
+}
+
+impl CgroupSpec {
+    fn bw(&self) -> f64 {
+        self.runtime_ms as f64 / self.period_ms as f64
+    }
+
+    fn depth(&self) -> usize {
+        self.name.matches('/').count()
+    }
+
+    fn is_parent_of(&self, other: &CgroupSpec) -> bool {
+        other.name.strip_prefix(&self.name)
+            .is_some_and(|rest| rest.starts_with('/'))
+    }
+}
+
+impl std::str::FromStr for CgroupSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, reservation) = s.split_once(':')
+            .ok_or_else(|| format!("Expected name:runtime/period, got {s}"))?;
+        let (runtime_ms, period_ms) = reservation.split_once('/')
+            .ok_or_else(|| format!("Expected runtime/period, got {reservation}"))?;
+
+        let parse = |value: &str| value.parse::<u64>()
+            .map_err(|err| format!("Invalid value {value} in {s}: {err}"));
+
+        let name = name.trim_matches('/');
+        if name.is_empty() || name == "." {
+            return Err("Cannot use the root cgroup".to_owned());
+        }
+
+        Ok(CgroupSpec { name: name.to_owned(), runtime_ms: parse(runtime_ms)?, period_ms: parse(period_ms)? })
+    }
+}
+
+impl std::fmt::Display for CgroupSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}/{}", self.name, self.runtime_ms, self.period_ms)
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroups of the hierarchy, as name:runtime/period in ms
+    /// [default: g0:50/100 g0/g1:20/100 g0/g2:25/100]
+    #[arg(short = 'g', long = "cgroup", value_name = "name:ms/ms")]
+    pub cgroups: Vec<CgroupSpec>,
+
+    /// tasks per cpu in the first leaf, to overload it
+    #[arg(long = "overload", default_value = "2", value_name = "u64")]
+    pub overload: u64,
+
+    /// max running time
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
+}
+
+fn default_hierarchy() -> Vec<CgroupSpec> {
+    ["g0:50/100", "g0/g1:20/100", "g0/g2:25/100"].iter()
+        .map(|spec| spec.parse().unwrap())
+        .collect()
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    if is_batch_test() && args.max_time.is_none() {
+        Err("Batch testing requires a maximum running time")?;
+    }
+
+    let mut cgroups =
+        if args.cgroups.is_empty() {
+            default_hierarchy()
+        } else {
+            args.cgroups.clone()
+        };
+
+    // parents before children
+    cgroups.sort_by_key(|cgroup| cgroup.depth());
+
+    let hierarchy: Vec<_> = cgroups.iter()
+        .map(|cgroup| cgroup.to_string())
+        .collect();
+    let test_header = format!("nested {} overload{}", hierarchy.join(","), args.overload);
+    let test_header =
+        if is_batch_test() {
+            test_header
+        } else {
+            test_header + " (Ctrl+C to stop)"
+        };
+
+    batch_test_header(&test_header, "time");
+
+    let result = main(&args, &cgroups, ctrlc_flag);
+    batch_test_result_stat(result)
+}
+
+pub fn main(args: &MyArgs, cgroups: &[CgroupSpec], ctrlc_flag: Option<ExitFlag>) -> Result<Vec<StatReport>, Box<dyn std::error::Error>> {
+    let leaves: Vec<_> = cgroups.iter()
+        .filter(|cgroup| !cgroups.iter().any(|child| cgroup.is_parent_of(child)))
+        .collect();
+
+    let num_cpus = CpuSet::all()?.num_cpus();
+    // each leaf uses its reservation on every cpu
+    let error = args.stats.tolerance.unwrap_or(0.01 * num_cpus as f64); // 1% of each cpu
+    let confidence = args.stats.confidence;
+
+    // cgroups are created parents first, and must be destroyed children
+    // first, also when dropped on errors
+    let mut my_cgroups: Vec<_> = cgroups.iter()
+        .map(|cgroup| MyCgroup::new(&cgroup.name, cgroup.runtime_ms * 1000, cgroup.period_ms * 1000, true))
+        .try_collect()?;
+    my_cgroups.reverse();
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    // enough hogs to use the whole reservation on every cpu, more in the
+    // first leaf, which must not steal its siblings' bandwidth
+    let procs: Vec<Vec<_>> = leaves.iter().enumerate()
+        .map(|(i, leaf)| {
+            let num_tasks = if i == 0 { args.overload.max(1) as usize * num_cpus } else { num_cpus };
+
+            (0..num_tasks)
+                .map(|_| {
+                    let proc = run_yes()?;
+                    migrate_task_to_cgroup(&leaf.name, proc.id())?;
+                    set_scheduler(proc.id(), SchedPolicy::RR(50))?;
+
+                    Ok::<_, Box<dyn std::error::Error>>(proc)
+                })
+                .try_collect()
+        })
+        .try_collect()?;
+
+    let mut meters: Vec<_> = procs.iter()
+        .map(|procs| {
+            let pids: Vec<_> = procs.iter().map(|proc| proc.id()).collect();
+            BandwidthMeter::new(&pids)
+        })
+        .try_collect()?;
+
+    let mut samples = collect_window_samples_multi(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || {
+            let leaves_bw: Vec<_> = meters.iter_mut()
+                .map(|meter| meter.sample())
+                .try_collect()?;
+            let total_bw = leaves_bw.iter().sum();
+
+            Ok(leaves_bw.into_iter().chain([total_bw]).collect())
+        }
+    )?;
+
+    procs.into_iter().flatten()
+        .try_for_each(|mut proc| proc.kill())?;
+
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+    my_cgroups.into_iter()
+        .try_for_each(|cgroup| cgroup.destroy())?;
+
+    // the leaves get exactly their reservation: neither the parents' unused
+    // bandwidth nor the siblings'
+    let total_samples = samples.pop().unwrap();
+    let total_bw: f64 = leaves.iter().map(|leaf| leaf.bw() * num_cpus as f64).sum();
+
+    leaves.iter().zip(samples)
+        .map(|(leaf, samples)| check_samples(&format!("Cgroup {}", leaf.name), samples, leaf.bw() * num_cpus as f64, error, confidence))
+        .chain([check_samples("All leaves", total_samples, total_bw, error * leaves.len() as f64, confidence)])
+        .try_collect()
+}
@@ -0,0 +1,166 @@
+use hcbs_test_suite::prelude::*;
+
+/// A cgroup's reservation, written as `runtime/period` (in ms).
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+pub struct Reservation {
+    pub runtime_ms: u64,
+    pub period_ms: u64,
+}
+
+impl std::str::FromStr for Reservation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (runtime_ms, period_ms) = s.split_once('/')
+            .ok_or_else(|| format!("Expected runtime/period, got {s}"))?;
+
+        let parse = |value: &str| value.parse::<u64>()
+            .map_err(|err| format!("Invalid value {value} in {s}: {err}"));
+
+        Ok(Reservation { runtime_ms: parse(runtime_ms)?, period_ms: parse(period_ms)? })
+    }
+}
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup's name
+    #[arg(short = 'c', long = "cgroup", default_value = "g0", value_name = "name")]
+    pub cgroup: String,
+
+    /// cgroup's reservations to test, as runtime/period in ms
+    #[arg(short = 'R', long = "reservation", value_name = "ms/ms", default_values = ["30/100", "60/100"])]
+    pub reservations: Vec<Reservation>,
+
+    /// SCHED_RR timeslices to test; the measurement windows must span many
+    /// rounds of the tasks' timeslices
+    #[arg(short = 's', long = "timeslice", value_name = "ms: u64", default_values = ["1", "10", "25"])]
+    pub timeslices_ms: Vec<u64>,
+
+    /// number of SCHED_RR tasks
+    #[arg(short = 'n', long = "num-tasks", default_value = "3", value_name = "u64")]
+    pub num_tasks: u64,
+
+    /// cpu to run the tasks on
+    #[arg(long = "cpu", default_value = "0", value_name = "u32")]
+    pub cpu: u32,
+
+    /// max running time of each combination
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    pub max_time: Option<u64>,
+
+    #[command(flatten)]
+    pub stats: StatArgs,
+}
+
+pub fn batch_runner(args: MyArgs, ctrlc_flag: Option<ExitFlag>) -> Result<(), Box<dyn std::error::Error>> {
+    if (is_batch_test() || args.reservations.len() * args.timeslices_ms.len() > 1) && args.max_time.is_none() {
+        Err("Batch testing, or testing multiple combinations, requires a maximum running time")?;
+    }
+
+    if args.num_tasks < 2 {
+        Err("At least two tasks are required")?;
+    }
+
+    let ctrlc_flag = match ctrlc_flag {
+        Some(flag) => flag,
+        None => create_ctrlc_handler()?,
+    };
+
+    // the original timeslice is restored also when a combination fails or
+    // the test is stopped
+    let timeslice_orig_ms = get_sched_rr_timeslice_ms()?;
+    let run_all = || {
+        for timeslice_ms in args.timeslices_ms.iter() {
+            set_sched_rr_timeslice_ms(*timeslice_ms)?;
+            let timeslice_ms = get_sched_rr_timeslice_ms()?;
+
+            for reservation in args.reservations.iter() {
+                if ctrlc_flag.is_exit() {
+                    return Ok(());
+                }
+
+                run_combination(&args, reservation, timeslice_ms, ctrlc_flag.clone())?;
+            }
+        }
+
+        Ok(())
+    };
+
+    let result = run_all();
+
+    set_sched_rr_timeslice_ms(timeslice_orig_ms)?;
+    result
+}
+
+fn run_combination(args: &MyArgs, reservation: &Reservation, timeslice_ms: u64, ctrlc_flag: ExitFlag) -> Result<(), Box<dyn std::error::Error>> {
+    let test_header = format!("rr c{} n{} r{} p{} slice{}",
+        args.cgroup, args.num_tasks, reservation.runtime_ms, reservation.period_ms, timeslice_ms);
+    let test_header =
+        if is_batch_test() {
+            test_header
+        } else {
+            test_header + " (Ctrl+C to stop)"
+        };
+
+    batch_test_header(&test_header, "time");
+
+    let result = main(args, reservation, Some(ctrlc_flag));
+    batch_test_result_stat(result)
+}
+
+pub fn main(args: &MyArgs, reservation: &Reservation, ctrlc_flag: Option<ExitFlag>) -> Result<Vec<StatReport>, Box<dyn std::error::Error>> {
+    let cpu_set = CpuSet::single(args.cpu)?;
+    let error = args.stats.tolerance.unwrap_or(0.01);
+    let confidence = args.stats.confidence;
+
+    let cgroup = MyCgroup::new(&args.cgroup, reservation.runtime_ms * 1000, reservation.period_ms * 1000, true)?;
+
+    migrate_task_to_cgroup(".", std::process::id())?;
+    set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
+
+    let procs: Vec<_> = (0..args.num_tasks)
+        .map(|_| {
+            let proc = run_yes()?;
+            migrate_task_to_cgroup(&args.cgroup, proc.id())?;
+            set_cpuset_to_pid(proc.id(), &cpu_set)?;
+            set_scheduler(proc.id(), SchedPolicy::RR(50))?;
+
+            Ok::<_, Box<dyn std::error::Error>>(proc)
+        })
+        .try_collect()?;
+
+    let mut meters: Vec<_> = procs.iter()
+        .map(|proc| BandwidthMeter::new(&[proc.id()]))
+        .try_collect()?;
+
+    let mut samples = collect_window_samples_multi(
+        args.stats.window_secs(args.max_time),
+        args.stats.num_windows(args.max_time),
+        ctrlc_flag,
+        || {
+            let tasks_bw: Vec<_> = meters.iter_mut()
+                .map(|meter| meter.sample())
+                .try_collect()?;
+            let total_bw = tasks_bw.iter().sum();
+
+            Ok(tasks_bw.into_iter().chain([total_bw]).collect())
+        }
+    )?;
+
+    procs.into_iter()
+        .try_for_each(|mut proc| proc.kill())?;
+
+    set_scheduler(std::process::id(), SchedPolicy::other())?;
+    cgroup.destroy()?;
+
+    // the tasks share the cgroup's bandwidth equally, and use all of it
+    let total_samples = samples.pop().unwrap();
+    let total_bw = reservation.runtime_ms as f64 / reservation.period_ms as f64;
+    let task_bw = total_bw / args.num_tasks as f64;
+
+    samples.into_iter().enumerate()
+        .map(|(i, samples)| check_samples(&format!("Task {i}"), samples, task_bw, error, confidence))
+        .chain([check_samples("All tasks", total_samples, total_bw, error, confidence)])
+        .try_collect()
+}
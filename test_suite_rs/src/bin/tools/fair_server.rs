@@ -0,0 +1,46 @@
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// CPUs whose fair server is configured [default: all]
+    #[arg(long = "cpu-set", value_parser = <CpuSet as std::str::FromStr>::from_str)]
+    cpu_set: Option<CpuSet>,
+
+    /// fair server's runtime
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64")]
+    runtime_ms: Option<u64>,
+
+    /// fair server's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64")]
+    period_ms: Option<u64>,
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    mount_debug_fs()?;
+
+    let cpus = match &args.cpu_set {
+        Some(cpu_set) => cpu_set.cpus().to_vec(),
+        None => get_fair_server_cpus()?,
+    };
+
+    for cpu in cpus {
+        if args.runtime_ms.is_some() || args.period_ms.is_some() {
+            let runtime_us = match args.runtime_ms {
+                Some(ms) => ms * 1000,
+                None => get_fair_server_runtime_us(cpu)?,
+            };
+
+            let period_us = match args.period_ms {
+                Some(ms) => ms * 1000,
+                None => get_fair_server_period_us(cpu)?,
+            };
+
+            fair_server_setup(cpu, runtime_us, period_us)?;
+        }
+
+        println!("cpu{cpu}: runtime {} us / period {} us",
+            get_fair_server_runtime_us(cpu)?, get_fair_server_period_us(cpu)?);
+    }
+
+    Ok(())
+}
@@ -1,3 +1,5 @@
+use hcbs_test_suite::prelude::*;
+
 #[derive(clap::Parser, Debug)]
 pub struct MyArgs {
     /// enable HRTICK_DL
@@ -6,14 +8,7 @@ pub struct MyArgs {
 }
 
 pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
-    let feature_str =
-        if args.enable {
-            "HRTICK_DL"
-        } else {
-            "NO_HRTICK_DL"
-        };
-
-    std::fs::write("/sys/kernel/debug/sched/features", feature_str)?;
+    set_sched_feature("HRTICK_DL", args.enable)?;
 
     Ok(())
-}
\ No newline at end of file
+}
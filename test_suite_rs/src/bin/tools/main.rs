@@ -1,8 +1,16 @@
+#![feature(iterator_try_collect)]
+
 mod realtime_bw_change;
 mod move_rt_to_root_cgroup;
 mod cgroup_setup;
 mod hrtick;
 mod chrt;
+mod status;
+mod reset;
+mod run;
+mod monitor;
+mod fair_server;
+mod sched_features;
 
 #[derive(clap::Parser, Debug)]
 #[command(about, long_about = None)]
@@ -42,6 +50,53 @@ pub enum Command {
     /// CHRT process to SCHED_DEADLINE
     #[command(name = "chrt-deadline", verbatim_doc_comment)]
     ChrtDeadline(chrt::MyArgs),
+
+    /// Print the real-time configuration: cgroup tree, global and fair-server
+    /// bandwidths, and scheduling policy and affinity of the member tasks
+    #[command(name = "status", verbatim_doc_comment)]
+    Status(status::MyArgs),
+
+    /// Clean up after crashed tests: kill leftover workload processes, move
+    /// real-time tasks to the root control group, delete the test cgroups and
+    /// restore the default real-time and fair-server bandwidths
+    #[command(name = "reset", verbatim_doc_comment)]
+    Reset(reset::MyArgs),
+
+    /// Run a command in the given cgroup, with the given policy and affinity
+    ///
+    /// The cgroup, policy and affinity are set up before executing the command.
+    /// e.g.: tools run -c g0 -r 10 -p 100 -P fifo:50 --cpu-set 0-1 -- yes
+    #[command(name = "run", verbatim_doc_comment)]
+    Run(run::MyArgs),
+
+    /// Live bandwidth monitor of cgroups and their tasks
+    ///
+    /// Periodically shows, for each cgroup, the configured and used bandwidth,
+    /// the number of throttling events, and the per-CPU and per-task usage.
+    /// Usage is computed from the tasks' runtime counters (schedstat).
+    /// Two limits apply:
+    /// - per-CPU usage charges a thread's whole interval to the CPU it last ran
+    ///   on, so it is approximate for tasks migrating between CPUs;
+    /// - throttling events are counted by sampling sched/debug (requires
+    ///   DebugFS) --throttle-samples times per interval, so throttles shorter
+    ///   than the sampling period may be missed: the counts are a lower bound.
+    #[command(name = "monitor", verbatim_doc_comment)]
+    Monitor(monitor::MyArgs),
+
+    /// Show or change the runtime and period of the fair servers
+    ///
+    /// Without runtime and period, the current parameters are printed. Use the
+    /// cpu set to give different budgets to different CPUs.
+    #[command(name = "fair-server", verbatim_doc_comment)]
+    FairServer(fair_server::MyArgs),
+
+    /// List the scheduler features or change their state
+    ///
+    /// Without arguments, every feature is printed with its state. Otherwise
+    /// each argument enables (e.g. HRTICK_DL) or disables (e.g. NO_HRTICK_DL)
+    /// the given feature. Requires DebugFS.
+    #[command(name = "sched-features", verbatim_doc_comment)]
+    SchedFeatures(sched_features::MyArgs),
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -65,6 +120,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         CgroupBwChange(args) => cgroup_setup::main(args)?,
         HRTick(args) => hrtick::main(args)?,
         ChrtDeadline(args) => chrt::main(args)?,
+        Status(args) => status::main(args)?,
+        Reset(args) => reset::main(args)?,
+        Run(args) => run::main(args)?,
+        Monitor(args) => monitor::main(args)?,
+        FairServer(args) => fair_server::main(args)?,
+        SchedFeatures(args) => sched_features::main(args)?,
     };
 
     Ok(())
@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::time::Instant;
+
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::cgroup::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroups to monitor [default: all, except the root cgroup]
+    #[arg(short = 'c', long = "cgroup", value_name = "name")]
+    cgroups: Vec<String>,
+
+    /// refresh interval
+    #[arg(short = 'i', long = "interval", value_name = "sec: f32", default_value = "1")]
+    interval_secs: f32,
+
+    /// number of times the throttling state is sampled in each interval;
+    /// shorter throttles between two samples are not counted
+    #[arg(long = "throttle-samples", value_name = "u64", default_value = "10")]
+    throttle_samples: u64,
+
+    /// append the output instead of redrawing the screen (e.g. for serial consoles)
+    #[arg(long = "plain")]
+    plain: bool,
+
+    /// also log the samples to the given CSV file
+    #[arg(long = "csv", value_name = "path")]
+    csv: Option<String>,
+
+    /// max running time
+    #[arg(short = 't', long = "max-time", value_name = "sec: u64")]
+    max_time: Option<u64>,
+}
+
+const CSV_HEADER: &str = "time_s,cgroup,pid,cpu,configured_bw,used_bw,throttled";
+
+struct ThreadStat {
+    runtime_ns: u64,
+    cpu: u32,
+}
+
+struct TaskUsage {
+    pid: u32,
+    comm: String,
+    policy: String,
+    used_bw: f64,
+}
+
+struct CgroupUsage {
+    name: String,
+    configured_bw: Option<f64>,
+    used_bw: f64,
+    /// throttling events observed in the interval, None if sched/debug is not available
+    throttled: Option<u64>,
+    tasks: Vec<TaskUsage>,
+    /// CPU -> (used bandwidth, throttling events)
+    cpus: BTreeMap<u32, (f64, Option<u64>)>,
+}
+
+struct Monitor {
+    start: Instant,
+    last_sample: Instant,
+    last_runtimes: HashMap<u32, u64>,
+    // (cgroup, cpu) -> throttled state at the previous sample
+    last_throttled: HashMap<(String, u32), bool>,
+    throttle_events: HashMap<(String, u32), u64>,
+    has_sched_debug: bool,
+    csv: Option<std::fs::File>,
+}
+
+// every thread of the given process, with its cumulative runtime and the CPU
+// it last ran on (field 39 of /proc/<pid>/task/<tid>/stat)
+fn __read_threads(pid: u32) -> Vec<(u32, ThreadStat)> {
+    let Ok(entries) = std::fs::read_dir(format!("/proc/{pid}/task")) else { return Vec::new(); };
+
+    entries.filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|tid| tid.parse::<u32>().ok())
+        .filter_map(|tid| {
+            let runtime_ns = std::fs::read_to_string(format!("/proc/{pid}/task/{tid}/schedstat")).ok()?
+                .split_whitespace().next()?
+                .parse::<u64>().ok()?;
+
+            // the command name may contain spaces, skip it
+            let stat = std::fs::read_to_string(format!("/proc/{pid}/task/{tid}/stat")).ok()?;
+            let cpu = stat.rsplit_once(')')?.1
+                .split_whitespace().nth(36)?
+                .parse::<u32>().ok()?;
+
+            Some((tid, ThreadStat { runtime_ns, cpu }))
+        })
+        .collect()
+}
+
+fn __monitored_cgroups(args: &MyArgs) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    fn subtree(name: &str, out: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+        let path = __cgroup_path(name);
+        let mut children: Vec<_> = std::fs::read_dir(&path)
+            .map_err(|err| format!("Error in reading directory {path}: {err}"))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .map(|child| if name == "." { child } else { format!("{name}/{child}") })
+            .collect();
+        children.sort();
+
+        for child in children {
+            out.push(child.clone());
+            subtree(&child, out)?;
+        }
+
+        Ok(())
+    }
+
+    if !args.cgroups.is_empty() {
+        return Ok(args.cgroups.iter()
+            .filter(|cgroup| __cgroup_exists(cgroup))
+            .cloned()
+            .collect());
+    }
+
+    let mut cgroups = Vec::new();
+    subtree(".", &mut cgroups)?;
+    Ok(cgroups)
+}
+
+impl Monitor {
+    fn new(args: &MyArgs) -> Result<Self, Box<dyn std::error::Error>> {
+        let csv = match &args.csv {
+            Some(path) => {
+                let mut file = std::fs::OpenOptions::new().create(true).write(true).truncate(true)
+                    .open(path)
+                    .map_err(|err| format!("Error in opening CSV file {path}: {err}"))?;
+                writeln!(file, "{CSV_HEADER}")?;
+                Some(file)
+            },
+            None => None,
+        };
+
+        Ok(Self {
+            start: Instant::now(),
+            last_sample: Instant::now(),
+            last_runtimes: HashMap::new(),
+            last_throttled: HashMap::new(),
+            throttle_events: HashMap::new(),
+            has_sched_debug: SchedDebug::snapshot().is_ok(),
+            csv,
+        })
+    }
+
+    fn sample_throttling(&mut self, cgroups: &[String]) {
+        let Ok(sched_debug) = SchedDebug::snapshot() else { return; };
+
+        for cgroup in cgroups {
+            for rt_rq in sched_debug.rt_rqs(cgroup) {
+                let throttled = rt_rq.rt_throttled().unwrap_or(false);
+                let key = (cgroup.clone(), rt_rq.cpu);
+
+                let was_throttled = self.last_throttled.insert(key.clone(), throttled).unwrap_or(false);
+                if throttled && !was_throttled {
+                    *self.throttle_events.entry(key).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    fn sample_usage(&mut self, cgroups: &[String]) -> Vec<CgroupUsage> {
+        let elapsed_ns = self.last_sample.elapsed().as_nanos() as f64;
+        self.last_sample = Instant::now();
+
+        let mut runtimes = HashMap::new();
+        let mut usages = Vec::new();
+        for cgroup in cgroups {
+            let configured_bw = match (get_cgroup_runtime_us(cgroup), get_cgroup_period_us(cgroup)) {
+                (Ok(runtime_us), Ok(period_us)) if period_us > 0 => Some(runtime_us as f64 / period_us as f64),
+                _ => None,
+            };
+
+            let mut usage = CgroupUsage {
+                name: cgroup.clone(),
+                configured_bw,
+                used_bw: 0f64,
+                throttled: None,
+                tasks: Vec::new(),
+                cpus: BTreeMap::new(),
+            };
+
+            for pid in get_cgroup_pids(cgroup).unwrap_or_default() {
+                let mut task_bw = 0f64;
+                for (tid, stat) in __read_threads(pid) {
+                    // threads seen for the first time are accounted from the next interval
+                    let last_runtime_ns = self.last_runtimes.get(&tid).copied().unwrap_or(stat.runtime_ns);
+                    let bw = stat.runtime_ns.saturating_sub(last_runtime_ns) as f64 / elapsed_ns;
+                    runtimes.insert(tid, stat.runtime_ns);
+
+                    task_bw += bw;
+                    usage.cpus.entry(stat.cpu).or_insert((0f64, None)).0 += bw;
+                }
+
+                usage.used_bw += task_bw;
+                usage.tasks.push(TaskUsage {
+                    pid,
+                    comm: std::fs::read_to_string(format!("/proc/{pid}/comm"))
+                        .map(|comm| comm.trim().to_owned())
+                        .unwrap_or_else(|_| "?".to_owned()),
+                    policy: get_scheduler(pid)
+                        .map(|policy| format!("{policy}"))
+                        .unwrap_or_else(|_| "?".to_owned()),
+                    used_bw: task_bw,
+                });
+            }
+
+            if self.has_sched_debug {
+                usage.cpus.values_mut().for_each(|(_, events)| *events = Some(0));
+
+                let mut total_events = 0;
+                for ((name, cpu), events) in self.throttle_events.iter() {
+                    if name != cgroup { continue; }
+
+                    total_events += events;
+                    usage.cpus.entry(*cpu).or_insert((0f64, None)).1 = Some(*events);
+                }
+
+                usage.throttled = Some(total_events);
+            }
+
+            usages.push(usage);
+        }
+
+        self.last_runtimes = runtimes;
+        self.throttle_events.clear();
+
+        usages
+    }
+
+    fn log_csv(&mut self, usages: &[CgroupUsage]) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(csv) = self.csv.as_mut() else { return Ok(()); };
+
+        let time_s = self.start.elapsed().as_secs_f64();
+        let fmt_opt = |value: Option<String>| value.unwrap_or_default();
+
+        for usage in usages {
+            let configured_bw = fmt_opt(usage.configured_bw.map(|bw| format!("{bw:.5}")));
+            let throttled = fmt_opt(usage.throttled.map(|events| format!("{events}")));
+
+            writeln!(csv, "{time_s:.3},{},,,{configured_bw},{:.5},{throttled}", usage.name, usage.used_bw)?;
+
+            for task in usage.tasks.iter() {
+                writeln!(csv, "{time_s:.3},{},{},,,{:.5},", usage.name, task.pid, task.used_bw)?;
+            }
+
+            for (cpu, (used_bw, events)) in usage.cpus.iter() {
+                let throttled = fmt_opt(events.map(|events| format!("{events}")));
+                writeln!(csv, "{time_s:.3},{},,{cpu},{configured_bw},{used_bw:.5},{throttled}", usage.name)?;
+            }
+        }
+
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+fn print_usages(usages: &[CgroupUsage], args: &MyArgs, time_s: f64) {
+    if !args.plain {
+        // clear the screen and move the cursor to the top left corner
+        print!("\x1b[2J\x1b[H");
+    }
+
+    println!("[monitor] t = {time_s:.1} s, interval {} s", args.interval_secs);
+    println!("  (throttled: at least, sampled {} times per interval; cpuN: usage charged to the last CPU of each thread)",
+        args.throttle_samples.max(1));
+    if usages.is_empty() {
+        println!("  no cgroups to monitor");
+    }
+
+    for usage in usages {
+        let configured_bw = match usage.configured_bw {
+            Some(bw) => format!("{bw:.3}"),
+            None => "-".to_owned(),
+        };
+        let throttled = match usage.throttled {
+            Some(events) => format!("{events}"),
+            None => "n/a".to_owned(),
+        };
+
+        println!("  {}: used {:.3} cpus, configured {configured_bw} per cpu, throttled {throttled}",
+            usage.name, usage.used_bw);
+
+        for (cpu, (used_bw, events)) in usage.cpus.iter() {
+            let events = events.map(|events| format!(", throttled {events}")).unwrap_or_default();
+            println!("    cpu{cpu}: used {used_bw:.3}{events}");
+        }
+
+        for task in usage.tasks.iter() {
+            println!("    - {} {} {}: used {:.3}", task.pid, task.comm, task.policy, task.used_bw);
+        }
+    }
+
+    let _ = std::io::stdout().flush();
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    mount_cgroup_fs()?;
+    let _ = mount_debug_fs();
+
+    if args.interval_secs <= 0f32 {
+        Err("Interval must be positive")?;
+    }
+
+    let mut monitor = Monitor::new(&args)?;
+    let throttle_samples = args.throttle_samples.max(1);
+    let mut cgroups = __monitored_cgroups(&args)?;
+    let mut tick = 0u64;
+
+    // prime the runtime counters
+    monitor.sample_usage(&cgroups);
+
+    wait_loop_periodic_fn(args.interval_secs / throttle_samples as f32, args.max_time, None, || {
+        if monitor.has_sched_debug {
+            monitor.sample_throttling(&cgroups);
+        }
+
+        tick += 1;
+        if !tick.is_multiple_of(throttle_samples) {
+            return Ok(());
+        }
+
+        let usages = monitor.sample_usage(&cgroups);
+        print_usages(&usages, &args, monitor.start.elapsed().as_secs_f64());
+        monitor.log_csv(&usages)?;
+
+        // pick up cgroups created or destroyed in the meantime
+        cgroups = __monitored_cgroups(&args)?;
+
+        Ok(())
+    })?;
+
+    Ok(())
+}
@@ -7,19 +7,9 @@ pub struct MyArgs {
     bw_ms: u64,
 }
 
-pub fn set_fair_server_runtime_us(runtime_us: u64) -> Result<(), Box<dyn std::error::Error>> {
-    let runtime_ns = runtime_us * 1000;
-
-    for entry in std::fs::read_dir("/sys/kernel/debug/sched/fair_server")? {
-        let entry = entry?.path();
-        if entry.is_dir() {
-            let entry = entry.into_os_string().into_string().unwrap();
-            std::fs::write(format!("{entry}/runtime"), format!("{runtime_ns}"))
-                .map_err(|err| format!("Error in writing runtime {runtime_ns} ns to {entry}/runtime: {err}"))?;
-        }
-    }
-    
-    Ok(())
+fn set_all_fair_servers_runtime_us(runtime_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    get_fair_server_cpus()?.into_iter()
+        .try_for_each(|cpu| set_fair_server_runtime_us(cpu, runtime_us))
 }
 
 pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
@@ -33,11 +23,11 @@ pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
     let curr_runtime_us = get_system_rt_runtime_us()?;
 
     if target_runtime_us > curr_runtime_us {
-        set_fair_server_runtime_us(target_fair_server_us)?;
+        set_all_fair_servers_runtime_us(target_fair_server_us)?;
         set_system_rt_runtime_us(target_runtime_us)?;
     } else {
         set_system_rt_runtime_us(target_runtime_us)?;
-        set_fair_server_runtime_us(target_fair_server_us)?;
+        set_all_fair_servers_runtime_us(target_fair_server_us)?;
     }
 
     Ok(())
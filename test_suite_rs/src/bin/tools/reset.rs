@@ -0,0 +1,208 @@
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::cgroup::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroups to delete, along with their children [default: g0, g1, ...]
+    #[arg(short = 'c', long = "cgroup", value_name = "name")]
+    cgroups: Vec<String>,
+}
+
+// default kernel values
+const RT_PERIOD_US: u64 = 1000000;
+const RT_RUNTIME_US: u64 = 950000;
+const FAIR_SERVER_PERIOD_US: u64 = 1000000;
+const FAIR_SERVER_RUNTIME_US: u64 = 50000;
+
+fn is_in_cgroups(pid: u32, cgroups: &[String]) -> bool {
+    // the task may have exited in the meantime
+    let Ok(cgroup) = get_cgroup_of_pid(pid) else { return false; };
+
+    cgroups.iter().any(|name| cgroup == *name || cgroup.starts_with(&format!("{name}/")))
+}
+
+fn has_suite_marker(process: &sysinfo::Process) -> bool {
+    let marker = format!("{SUITE_MARKER_ENV}=1");
+    process.environ().iter().any(|var| *var == *marker)
+}
+
+// the tasks inside the suite's cgroups, or started by the suite, except for
+// this process and its ancestors (e.g. the test script)
+fn kill_workload(system: &sysinfo::System, cgroups: &[String], fixes: &mut Vec<String>) {
+    let mut ancestors = Vec::new();
+    let mut next = Some(sysinfo::Pid::from_u32(std::process::id()));
+    while let Some(pid) = next {
+        ancestors.push(pid);
+        next = system.process(pid).and_then(|process| process.parent());
+    }
+
+    for (pid, process) in system.processes() {
+        if ancestors.contains(pid) || process.thread_kind().is_some() {
+            continue;
+        }
+
+        if !is_in_cgroups(pid.as_u32(), cgroups) && !has_suite_marker(process) {
+            continue;
+        }
+
+        if process.kill() {
+            process.wait();
+            fixes.push(format!("Killed leftover process {pid} ({})", process.name().to_string_lossy()));
+        }
+    }
+}
+
+fn suite_cgroups(args: &MyArgs) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    if !args.cgroups.is_empty() {
+        return Ok(args.cgroups.clone());
+    }
+
+    let path = __cgroup_path(".");
+    let mut cgroups: Vec<_> = std::fs::read_dir(&path)
+        .map_err(|err| format!("Error in reading directory {path}: {err}"))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.strip_prefix('g')
+            .is_some_and(|num| !num.is_empty() && num.chars().all(|c| c.is_ascii_digit())))
+        .collect();
+
+    cgroups.sort();
+    Ok(cgroups)
+}
+
+// the given cgroup and all its descendants, children first
+fn cgroup_subtree(name: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let path = __cgroup_path(name);
+    let mut subtree = Vec::new();
+    for entry in std::fs::read_dir(&path)
+        .map_err(|err| format!("Error in reading directory {path}: {err}"))?
+    {
+        let entry = entry?;
+        if !entry.path().is_dir() { continue; }
+
+        let Ok(child) = entry.file_name().into_string() else { continue; };
+        subtree.append(&mut cgroup_subtree(&format!("{name}/{child}"))?);
+    }
+
+    subtree.push(name.to_owned());
+    Ok(subtree)
+}
+
+// give a leftover task of the suite back to SCHED_OTHER and the root cgroup
+fn reset_task(pid: u32, cgroup: &str, fixes: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    use SchedPolicy::*;
+
+    let policy = get_scheduler(pid)?;
+    if let FIFO(_) | RR(_) | DEADLINE {..} = policy {
+        set_scheduler(pid, SchedPolicy::other())?;
+        fixes.push(format!("Reset task {pid} from {policy} to SCHED_OTHER"));
+    }
+
+    migrate_task_to_cgroup(".", pid)?;
+    fixes.push(format!("Migrated task {pid} from cgroup {cgroup} to the root cgroup"));
+
+    Ok(())
+}
+
+fn delete_cgroups(cgroups: &[String], fixes: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    for cgroup in cgroups {
+        if !__cgroup_exists(cgroup) { continue; }
+
+        // the remaining tasks, including the real-time ones, are moved to the
+        // root cgroup before deleting their cgroup
+        for name in cgroup_subtree(cgroup)? {
+            for pid in get_cgroup_pids(&name)? {
+                match reset_task(pid, &name, fixes) {
+                    // the task exited in the meantime, nothing left to do
+                    Err(_) if !std::path::Path::new(&format!("/proc/{pid}")).exists() => (),
+                    result => result?,
+                }
+            }
+
+            delete_cgroup(&name)?;
+            fixes.push(format!("Deleted cgroup {name}"));
+        }
+    }
+
+    Ok(())
+}
+
+fn restore_fair_servers(fixes: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    // no fair servers on this kernel
+    let Ok(cpus) = get_fair_server_cpus() else { return Ok(()); };
+
+    for cpu in cpus {
+        let runtime_us = get_fair_server_runtime_us(cpu)?;
+        let period_us = get_fair_server_period_us(cpu)?;
+        if runtime_us == FAIR_SERVER_RUNTIME_US && period_us == FAIR_SERVER_PERIOD_US {
+            continue;
+        }
+
+        fair_server_setup(cpu, FAIR_SERVER_RUNTIME_US, FAIR_SERVER_PERIOD_US)?;
+        fixes.push(format!("Restored fair server of CPU {cpu} from {runtime_us}/{period_us} us to {FAIR_SERVER_RUNTIME_US}/{FAIR_SERVER_PERIOD_US} us"));
+    }
+
+    Ok(())
+}
+
+fn restore_rt_bandwidth(fixes: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let period_us = get_system_rt_period_us()?;
+    if period_us != RT_PERIOD_US {
+        set_system_rt_period_us(RT_PERIOD_US)?;
+        fixes.push(format!("Restored sched_rt_period_us from {period_us} to {RT_PERIOD_US}"));
+    }
+
+    // sched_rt_runtime_us is -1 when real-time throttling is disabled
+    let runtime_us = std::fs::read_to_string("/proc/sys/kernel/sched_rt_runtime_us")
+        .map_err(|err| format!("Error in reading from /proc/sys/kernel/sched_rt_runtime_us: {err}"))?
+        .trim().to_owned();
+    if runtime_us != format!("{RT_RUNTIME_US}") {
+        set_system_rt_runtime_us(RT_RUNTIME_US)?;
+        fixes.push(format!("Restored sched_rt_runtime_us from {runtime_us} to {RT_RUNTIME_US}"));
+    }
+
+    Ok(())
+}
+
+fn reset(args: &MyArgs, fixes: &mut Vec<String>) -> Result<(), Box<dyn std::error::Error>> {
+    let cgroups: Vec<_> = suite_cgroups(args)?.into_iter()
+        .filter(|cgroup| cgroup != ".")
+        .collect();
+
+    let system = sysinfo::System::new_all();
+    kill_workload(&system, &cgroups, fixes);
+    delete_cgroups(&cgroups, fixes)?;
+
+    // give back real-time bandwidth before growing the fair servers, and
+    // vice-versa, so that the total never exceeds the CPU capacity. A failure
+    // in one of the two does not prevent restoring the other.
+    let rt_runtime_us = get_system_rt_runtime_us().unwrap_or(0);
+    if rt_runtime_us > RT_RUNTIME_US {
+        let rt_result = restore_rt_bandwidth(fixes);
+        let fair_result = restore_fair_servers(fixes);
+        rt_result.and(fair_result)
+    } else {
+        let fair_result = restore_fair_servers(fixes);
+        let rt_result = restore_rt_bandwidth(fixes);
+        fair_result.and(rt_result)
+    }
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    mount_cgroup_fs()?;
+    mount_debug_fs()?;
+
+    let mut fixes = Vec::new();
+    let result = reset(&args, &mut fixes);
+
+    if fixes.is_empty() && result.is_ok() {
+        println!("Nothing to reset");
+    }
+
+    for fix in fixes.iter() {
+        println!("{fix}");
+    }
+
+    result
+}
@@ -0,0 +1,68 @@
+use std::os::unix::process::CommandExt;
+
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// cgroup to run the command in
+    #[arg(short = 'c', long = "cgroup", value_name = "name")]
+    cgroup: Option<String>,
+
+    /// cgroup's runtime (the cgroup is created if it does not exist)
+    #[arg(short = 'r', long = "runtime", value_name = "ms: u64", requires_all = ["cgroup", "period_ms"])]
+    runtime_ms: Option<u64>,
+
+    /// cgroup's period
+    #[arg(short = 'p', long = "period", value_name = "ms: u64", requires_all = ["cgroup", "runtime_ms"])]
+    period_ms: Option<u64>,
+
+    /// scheduling policy: other[:nice], batch[:nice], idle, fifo:prio, rr:prio,
+    /// deadline:runtime/deadline/period (ms)
+    #[arg(short = 'P', long = "policy", value_name = "policy")]
+    policy: Option<SchedPolicy>,
+
+    /// sched_setattr flags, replacing the default ones
+    #[arg(short = 'f', long = "flags", value_name = "flag", value_delimiter = ',', requires = "policy")]
+    flags: Option<Vec<SchedFlag>>,
+
+    /// allowed cpus
+    #[arg(long = "cpu-set", value_parser = <CpuSet as std::str::FromStr>::from_str)]
+    cpu_set: Option<CpuSet>,
+
+    /// command to execute
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "command")]
+    command: Vec<String>,
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let pid = std::process::id();
+
+    // The process is set up and then replaced with the command, so that the
+    // command never runs outside of the requested reservation.
+    if let Some(cgroup) = &args.cgroup {
+        mount_cgroup_fs()?;
+
+        if let (Some(runtime_ms), Some(period_ms)) = (args.runtime_ms, args.period_ms) {
+            cgroup_setup(cgroup, runtime_ms * 1000, period_ms * 1000)?;
+        }
+
+        migrate_task_to_cgroup(cgroup, pid)?;
+    }
+
+    // SCHED_DEADLINE tasks cannot change their affinity
+    if let Some(cpu_set) = &args.cpu_set {
+        set_cpuset_to_pid(pid, cpu_set)?;
+    }
+
+    match (args.policy, &args.flags) {
+        (Some(policy), Some(flags)) => set_scheduler_with_flags(pid, policy, flags)?,
+        (Some(policy), None) => set_scheduler(pid, policy)?,
+        (None, _) => (),
+    };
+
+    let err = std::process::Command::new(&args.command[0])
+        .args(&args.command[1..])
+        .exec();
+
+    Err(format!("Error in executing {}: {err}", args.command[0]))?
+}
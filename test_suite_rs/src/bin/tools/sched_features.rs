@@ -0,0 +1,27 @@
+use hcbs_test_suite::prelude::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// features to set, e.g. HRTICK_DL to enable it, NO_HRTICK_DL to disable it
+    #[arg(value_name = "feature")]
+    features: Vec<SchedFeature>,
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    mount_debug_fs()?;
+
+    if args.features.is_empty() {
+        for (name, enabled) in get_sched_features()? {
+            println!("{name:<28} {}", if enabled { "on" } else { "off" });
+        }
+
+        return Ok(());
+    }
+
+    for feature in args.features.iter() {
+        set_sched_feature(&feature.name, feature.enabled)?;
+        println!("{feature}");
+    }
+
+    Ok(())
+}
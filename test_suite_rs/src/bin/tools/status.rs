@@ -0,0 +1,201 @@
+use hcbs_test_suite::prelude::*;
+use hcbs_test_suite::cgroup::*;
+
+#[derive(clap::Parser, Debug)]
+pub struct MyArgs {
+    /// list all the tasks of the root cgroup, not just the real-time ones
+    #[arg(short = 'a', long = "all-tasks")]
+    all_tasks: bool,
+
+    /// print the status in JSON format
+    #[arg(long = "json")]
+    json: bool,
+}
+
+#[derive(serde::Serialize)]
+struct Status {
+    sched_rt_period_us: u64,
+    sched_rt_runtime_us: i64,
+    fair_servers: Vec<FairServerStatus>,
+    cgroups: CgroupStatus,
+}
+
+#[derive(serde::Serialize)]
+struct FairServerStatus {
+    cpu: u32,
+    runtime_us: u64,
+    period_us: u64,
+}
+
+#[derive(serde::Serialize)]
+struct CgroupStatus {
+    name: String,
+    cpu_controller: bool,
+    runtime_us: Option<u64>,
+    period_us: Option<u64>,
+    bandwidth: Option<f64>,
+    tasks: Vec<TaskStatus>,
+    children: Vec<CgroupStatus>,
+}
+
+#[derive(serde::Serialize)]
+struct TaskStatus {
+    pid: u32,
+    comm: String,
+    policy: String,
+    affinity: String,
+}
+
+fn fair_servers_status() -> Result<Vec<FairServerStatus>, Box<dyn std::error::Error>> {
+    get_fair_server_cpus()?.into_iter()
+        .map(|cpu| Ok(FairServerStatus {
+            cpu,
+            runtime_us: get_fair_server_runtime_us(cpu)?,
+            period_us: get_fair_server_period_us(cpu)?,
+        }))
+        .try_collect()
+}
+
+fn task_status(pid: u32) -> TaskStatus {
+    let comm = std::fs::read_to_string(format!("/proc/{pid}/comm"))
+        .map(|comm| comm.trim().to_owned())
+        .unwrap_or_else(|_| "?".to_owned());
+    let policy = get_scheduler(pid)
+        .map(|policy| format!("{policy}"))
+        .unwrap_or_else(|err| format!("<{err}>"));
+    let affinity = get_cpuset_of_pid(pid)
+        .map(|cpus| format!("{cpus}"))
+        .unwrap_or_else(|err| format!("<{err}>"));
+
+    TaskStatus { pid, comm, policy, affinity }
+}
+
+fn is_realtime(pid: u32) -> bool {
+    use SchedPolicy::*;
+
+    matches!(get_scheduler(pid), Ok(FIFO(_)) | Ok(RR(_)) | Ok(DEADLINE {..}))
+}
+
+#[cfg(not(feature = "cgroup_v2"))]
+fn has_cpu_controller(_name: &str) -> bool {
+    true
+}
+
+#[cfg(feature = "cgroup_v2")]
+fn has_cpu_controller(name: &str) -> bool {
+    let parent = std::path::Path::new(name).parent()
+        .and_then(|parent| parent.to_str())
+        .filter(|parent| !parent.is_empty())
+        .unwrap_or(".");
+
+    name == "." || __is_cpu_contoller_v2_enabled(parent).unwrap_or(false)
+}
+
+fn cgroup_status(name: &str, args: &MyArgs) -> Result<CgroupStatus, Box<dyn std::error::Error>> {
+    let runtime_us = get_cgroup_runtime_us(name).ok();
+    let period_us = get_cgroup_period_us(name).ok();
+    let bandwidth = match (runtime_us, period_us) {
+        (Some(runtime_us), Some(period_us)) if period_us > 0 => Some(runtime_us as f64 / period_us as f64),
+        _ => None,
+    };
+
+    let tasks = get_cgroup_pids(name)?.into_iter()
+        .filter(|pid| name != "." || args.all_tasks || is_realtime(*pid))
+        .map(task_status)
+        .collect();
+
+    let path = __cgroup_path(name);
+    let mut children_names: Vec<_> = std::fs::read_dir(&path)
+        .map_err(|err| format!("Error in reading directory {path}: {err}"))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .map(|child| if name == "." { child } else { format!("{name}/{child}") })
+        .collect();
+    children_names.sort();
+
+    let children = children_names.iter()
+        .map(|child| cgroup_status(child, args))
+        .try_collect()?;
+
+    Ok(CgroupStatus {
+        name: name.to_owned(),
+        cpu_controller: has_cpu_controller(name),
+        runtime_us,
+        period_us,
+        bandwidth,
+        tasks,
+        children,
+    })
+}
+
+fn print_cgroup(cgroup: &CgroupStatus, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let name = std::path::Path::new(&cgroup.name).file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("/");
+
+    let bandwidth = match (cgroup.runtime_us, cgroup.period_us, cgroup.bandwidth) {
+        (Some(runtime_us), Some(period_us), Some(bw)) =>
+            format!("runtime {runtime_us} us / period {period_us} us (bw {bw:.3})"),
+        _ => "no rt bandwidth".to_owned(),
+    };
+
+    let controller = if cgroup.cpu_controller { "cpu" } else { "no cpu controller" };
+
+    println!("{indent}{name} [{controller}] {bandwidth}");
+    for task in cgroup.tasks.iter() {
+        println!("{indent}  - {} {} {} cpus {}", task.pid, task.comm, task.policy, task.affinity);
+    }
+
+    for child in cgroup.children.iter() {
+        print_cgroup(child, depth + 1);
+    }
+}
+
+pub fn main(args: MyArgs) -> Result<(), Box<dyn std::error::Error>> {
+    mount_cgroup_fs()?;
+    mount_debug_fs()?;
+
+    let status = Status {
+        sched_rt_period_us: get_system_rt_period_us()?,
+        // sched_rt_runtime_us is -1 when real-time throttling is disabled
+        sched_rt_runtime_us: std::fs::read_to_string("/proc/sys/kernel/sched_rt_runtime_us")
+            .map_err(|err| format!("Error in reading from /proc/sys/kernel/sched_rt_runtime_us: {err}"))?
+            .trim().parse::<i64>()
+            .map_err(|err| format!("Error in parsing /proc/sys/kernel/sched_rt_runtime_us: {err}"))?,
+        fair_servers: fair_servers_status().unwrap_or_default(),
+        cgroups: cgroup_status(".", &args)?,
+    };
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    let global_bw =
+        if status.sched_rt_runtime_us < 0 {
+            "unlimited".to_owned()
+        } else {
+            format!("{:.3}", status.sched_rt_runtime_us as f64 / status.sched_rt_period_us as f64)
+        };
+
+    println!("Global: sched_rt_runtime_us {} / sched_rt_period_us {} (bw {global_bw})",
+        status.sched_rt_runtime_us, status.sched_rt_period_us);
+
+    println!("Fair servers:");
+    if status.fair_servers.is_empty() {
+        println!("  not available");
+    }
+
+    for server in status.fair_servers.iter() {
+        println!("  cpu{}: runtime {} us / period {} us (bw {:.3})",
+            server.cpu, server.runtime_us, server.period_us,
+            server.runtime_us as f64 / server.period_us as f64);
+    }
+
+    println!("Cgroups:");
+    print_cgroup(&status.cgroups, 1);
+
+    Ok(())
+}
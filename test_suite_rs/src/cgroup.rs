@@ -12,7 +12,9 @@ pub mod prelude {
         get_system_rt_period_us,
         get_system_rt_runtime_us,
         set_system_rt_period_us,
-        set_system_rt_runtime_us
+        set_system_rt_runtime_us,
+        get_sched_rr_timeslice_ms,
+        set_sched_rr_timeslice_ms,
     };
 }
 
@@ -189,6 +191,25 @@ pub fn set_system_rt_runtime_us(runtime_us: u64) -> Result<(), Box<dyn std::erro
     Ok(())
 }
 
+pub fn get_sched_rr_timeslice_ms() -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(
+        std::fs::read_to_string("/proc/sys/kernel/sched_rr_timeslice_ms")
+            .map_err(|err| format!("Error in reading from /proc/sys/kernel/sched_rr_timeslice_ms: {err}"))
+        .and_then(|s| s.trim().parse::<u64>()
+            .map_err(|err| format!("Error in parsing /proc/sys/kernel/sched_rr_timeslice_ms: {err}")))?
+    )
+}
+
+// The kernel rounds the timeslice to a whole number of jiffies.
+pub fn set_sched_rr_timeslice_ms(timeslice_ms: u64) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write("/proc/sys/kernel/sched_rr_timeslice_ms", format!("{timeslice_ms}"))
+        .map_err(|err| format!("Error in writing timeslice {timeslice_ms} ms to /proc/sys/kernel/sched_rr_timeslice_ms: {err}"))?;
+
+    __println_debug(|| format!("Set timeslice {timeslice_ms} ms to /proc/sys/kernel/sched_rr_timeslice_ms"));
+
+    Ok(())
+}
+
 pub fn create_cgroup(name: &str) -> Result<(), Box<dyn std::error::Error>> {
     mount_cgroup_fs()?;
 
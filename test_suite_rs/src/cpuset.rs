@@ -7,7 +7,12 @@ pub mod prelude {
         CpuSet,
         CpuSetUnchecked,
         CpuSetBuildError,
-        set_cpuset_to_pid
+        CpuTopology,
+        CpuPlacement,
+        set_cpuset_to_pid,
+        get_cpuset_of_pid,
+        get_hotpluggable_cpus,
+        set_cpu_online,
     };
 }
 
@@ -24,6 +29,7 @@ pub enum CpuSetBuildError {
     ParseError(String),
     UnavailableCPU(u32),
     UnavailableCPUs,
+    UnavailableNode(u32),
 }
 
 impl std::fmt::Display for CpuSetBuildError {
@@ -35,6 +41,7 @@ impl std::fmt::Display for CpuSetBuildError {
             CpuSetBuildError::ParseError(error) => write!(f, "Parse error: {error}"),
             CpuSetBuildError::UnavailableCPU(cpu) => write!(f, "Requesting unavailable cpu {cpu}"),
             CpuSetBuildError::UnavailableCPUs => write!(f, "Requesting more CPUs than available ones"),
+            CpuSetBuildError::UnavailableNode(node) => write!(f, "Requesting unavailable NUMA node {node}"),
         }
     }
 }
@@ -77,9 +84,279 @@ impl CpuSet {
         })
     }
 
+    /// The first hardware thread of each of the first `num_cpus` physical
+    /// cores, so that no two CPUs of the set are hyperthreads of the same core.
+    /// Fails if there are fewer than `num_cpus` cores.
+    pub fn one_per_core(num_cpus: u64) -> Result<CpuSet, CpuSetBuildError> {
+        let (first_threads, _) = __split_first_threads(CpuTopology::all()?);
+        let cpus: Vec<_> = first_threads.into_iter()
+            .take(num_cpus as usize)
+            .map(|topology| topology.cpu)
+            .collect();
+
+        if cpus.len() < num_cpus as usize {
+            return Err(CpuSetBuildError::UnavailableCPUs);
+        }
+
+        Ok(CpuSet { cpus })
+    }
+
+    /// `num_cpus` CPUs of the same physical package, preferring distinct
+    /// cores over hyperthreads of the same core.
+    pub fn same_package(num_cpus: u64) -> Result<CpuSet, CpuSetBuildError> {
+        let topology = CpuTopology::all()?;
+
+        let mut packages: Vec<_> = topology.iter().map(|topology| topology.package_id).collect();
+        packages.sort();
+        packages.dedup();
+
+        packages.into_iter()
+            .map(|package| {
+                __one_per_core_first(topology.iter()
+                    .filter(|topology| topology.package_id == package)
+                    .cloned()
+                    .collect())
+            })
+            .find(|package| package.len() >= num_cpus as usize)
+            .map(|package| CpuSet {
+                cpus: __sorted(package.into_iter()
+                    .take(num_cpus as usize)
+                    .map(|topology| topology.cpu)
+                    .collect())
+            })
+            .ok_or(CpuSetBuildError::UnavailableCPUs)
+    }
+
+    /// The online CPUs of the given NUMA node.
+    pub fn numa_node(node: u32) -> Result<CpuSet, CpuSetBuildError> {
+        let cpus: Vec<_> = CpuTopology::all()?.into_iter()
+            .filter(|topology| topology.node == Some(node))
+            .map(|topology| topology.cpu)
+            .collect();
+
+        if cpus.is_empty() {
+            return Err(CpuSetBuildError::UnavailableNode(node));
+        }
+
+        Ok(CpuSet { cpus })
+    }
+
     pub fn num_cpus(&self) -> usize {
         self.cpus.len()
     }
+
+    pub fn cpus(&self) -> &[u32] {
+        &self.cpus
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.cpus.iter().copied()
+    }
+
+    pub fn contains(&self, cpu: u32) -> bool {
+        self.cpus.contains(&cpu)
+    }
+
+    pub fn union(&self, other: &CpuSet) -> CpuSet {
+        CpuSet { cpus: __union(&self.cpus, &other.cpus) }
+    }
+
+    pub fn intersection(&self, other: &CpuSet) -> CpuSet {
+        CpuSet { cpus: __intersection(&self.cpus, &other.cpus) }
+    }
+
+    pub fn difference(&self, other: &CpuSet) -> CpuSet {
+        CpuSet { cpus: __difference(&self.cpus, &other.cpus) }
+    }
+
+    /// The online CPUs which are not in the set.
+    pub fn complement(&self) -> Result<CpuSet, CpuSetBuildError> {
+        Ok(CpuSet::all()?.difference(self))
+    }
+
+    pub fn is_subset(&self, other: &CpuSet) -> bool {
+        __is_subset(&self.cpus, &other.cpus)
+    }
+
+    /// Kernel hex mask, as in /proc/irq/*/smp_affinity: comma separated 32-bit
+    /// words, most significant first (e.g. `00000001,0000000f`).
+    pub fn to_hex_mask(&self) -> String {
+        __cpus_to_hex_mask(&self.cpus)
+    }
+
+    pub fn from_hex_mask(mask: &str) -> Result<CpuSet, CpuSetBuildError> {
+        CpuSetUnchecked::from_hex_mask(mask)
+            .map_err(CpuSetBuildError::ParseError)?
+            .into()
+    }
+}
+
+impl<'a> IntoIterator for &'a CpuSet {
+    type Item = u32;
+    type IntoIter = std::iter::Copied<std::slice::Iter<'a, u32>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cpus.iter().copied()
+    }
+}
+
+/// Position of an online CPU in the machine's topology, as reported in
+/// /sys/devices/system/cpu/cpu<N>/topology and /sys/devices/system/node.
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct CpuTopology {
+    pub cpu: u32,
+    /// core identifier, unique only within its package
+    pub core_id: u32,
+    pub package_id: u32,
+    /// None if the kernel does not expose NUMA information
+    pub node: Option<u32>,
+}
+
+fn __read_topology_id(cpu: u32, file: &str) -> Result<u32, CpuSetBuildError> {
+    let path = format!("/sys/devices/system/cpu/cpu{cpu}/topology/{file}");
+
+    std::fs::read_to_string(&path)
+        .map_err(CpuSetBuildError::IO)?
+        .trim().parse::<u32>()
+        .map_err(|err| CpuSetBuildError::ParseError(format!("{path}: {err}")))
+}
+
+// NUMA node of each CPU, from /sys/devices/system/node/node<K>/cpulist
+fn __read_numa_nodes() -> Result<Vec<(u32, CpuSetUnchecked)>, CpuSetBuildError> {
+    let node_dir = "/sys/devices/system/node";
+    if !std::fs::exists(node_dir).map_err(CpuSetBuildError::IO)? {
+        return Ok(Vec::new());
+    }
+
+    std::fs::read_dir(node_dir)
+        .map_err(CpuSetBuildError::IO)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|name| name.strip_prefix("node")?.parse::<u32>().ok())
+        .map(|node| {
+            let cpus = std::fs::read_to_string(format!("{node_dir}/node{node}/cpulist"))
+                .map_err(CpuSetBuildError::IO)?;
+
+            // nodes without CPUs (e.g. memory only) have an empty list
+            if cpus.trim().is_empty() {
+                return Ok((node, CpuSetUnchecked::empty()));
+            }
+
+            CpuSetUnchecked::from_str(cpus.trim())
+                .map(|cpus| (node, cpus))
+                .map_err(CpuSetBuildError::ParseError)
+        })
+        .collect()
+}
+
+// the first hardware thread of each core, and the remaining threads, both in
+// CPU order
+fn __split_first_threads(topology: Vec<CpuTopology>) -> (Vec<CpuTopology>, Vec<CpuTopology>) {
+    let mut seen_cores = Vec::new();
+    topology.into_iter()
+        .partition(|topology| {
+            let core = (topology.package_id, topology.core_id);
+            if seen_cores.contains(&core) {
+                false
+            } else {
+                seen_cores.push(core);
+                true
+            }
+        })
+}
+
+// the first hardware thread of each core in CPU order, followed by the
+// remaining threads
+fn __one_per_core_first(topology: Vec<CpuTopology>) -> Vec<CpuTopology> {
+    let (first, siblings) = __split_first_threads(topology);
+
+    first.into_iter().chain(siblings).collect()
+}
+
+impl CpuTopology {
+    /// Topology of every online CPU, in ascending CPU order.
+    pub fn all() -> Result<Vec<CpuTopology>, CpuSetBuildError> {
+        let nodes = __read_numa_nodes()?;
+
+        CpuSet::all()?.cpus.into_iter()
+            .map(|cpu| {
+                Ok(CpuTopology {
+                    cpu,
+                    core_id: __read_topology_id(cpu, "core_id")?,
+                    package_id: __read_topology_id(cpu, "physical_package_id")?,
+                    node: nodes.iter()
+                        .find(|(_, cpus)| cpus.cpus.contains(&cpu))
+                        .map(|(node, _)| *node),
+                })
+            })
+            .collect()
+    }
+}
+
+/// How to choose a given number of CPUs for a test.
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq)]
+pub enum CpuPlacement {
+    /// the first online CPUs (see [CpuSet::any_subset])
+    First,
+    /// see [CpuSet::one_per_core]
+    OnePerCore,
+    /// see [CpuSet::same_package]
+    SamePackage,
+    /// the first CPUs of the given NUMA node (see [CpuSet::numa_node])
+    NumaNode(u32),
+}
+
+impl CpuPlacement {
+    pub fn cpu_set(&self, num_cpus: u64) -> Result<CpuSet, CpuSetBuildError> {
+        match self {
+            CpuPlacement::First => CpuSet::any_subset(num_cpus),
+            CpuPlacement::OnePerCore => CpuSet::one_per_core(num_cpus),
+            CpuPlacement::SamePackage => CpuSet::same_package(num_cpus),
+            CpuPlacement::NumaNode(node) => {
+                let node_cpus = CpuSet::numa_node(*node)?;
+                if num_cpus as usize > node_cpus.cpus.len() {
+                    return Err(CpuSetBuildError::UnavailableCPUs);
+                }
+
+                Ok(CpuSet { cpus: node_cpus.cpus.into_iter().take(num_cpus as usize).collect() })
+            },
+        }
+    }
+}
+
+impl FromStr for CpuPlacement {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim_ascii() {
+            "first" => Ok(CpuPlacement::First),
+            "one-per-core" => Ok(CpuPlacement::OnePerCore),
+            "same-package" => Ok(CpuPlacement::SamePackage),
+            other => {
+                let node = other.strip_prefix("numa:")
+                    .ok_or_else(|| format!("Unknown placement {other}, expected one of: first, one-per-core, same-package, numa:<node>"))?;
+
+                node.parse::<u32>()
+                    .map(CpuPlacement::NumaNode)
+                    .map_err(|err| format!("Invalid NUMA node {node}: {err}"))
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for CpuPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CpuPlacement::First => write!(f, "first"),
+            CpuPlacement::OnePerCore => write!(f, "one-per-core"),
+            CpuPlacement::SamePackage => write!(f, "same-package"),
+            CpuPlacement::NumaNode(node) => write!(f, "numa:{node}"),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -114,6 +391,131 @@ impl CpuSetUnchecked {
     pub fn num_cpus(&self) -> usize {
         self.cpus.len()
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.cpus.iter().copied()
+    }
+
+    pub fn contains(&self, cpu: u32) -> bool {
+        self.cpus.contains(&cpu)
+    }
+
+    pub fn union(&self, other: &CpuSetUnchecked) -> CpuSetUnchecked {
+        CpuSetUnchecked { cpus: __union(&self.cpus, &other.cpus) }
+    }
+
+    pub fn intersection(&self, other: &CpuSetUnchecked) -> CpuSetUnchecked {
+        CpuSetUnchecked { cpus: __intersection(&self.cpus, &other.cpus) }
+    }
+
+    pub fn difference(&self, other: &CpuSetUnchecked) -> CpuSetUnchecked {
+        CpuSetUnchecked { cpus: __difference(&self.cpus, &other.cpus) }
+    }
+
+    pub fn is_subset(&self, other: &CpuSetUnchecked) -> bool {
+        __is_subset(&self.cpus, &other.cpus)
+    }
+
+    pub fn to_hex_mask(&self) -> String {
+        __cpus_to_hex_mask(&self.cpus)
+    }
+
+    pub fn from_hex_mask(mask: &str) -> Result<CpuSetUnchecked, String> {
+        Ok(CpuSetUnchecked { cpus: __cpus_from_hex_mask(mask)? })
+    }
+}
+
+impl From<CpuSet> for CpuSetUnchecked {
+    fn from(cpu_set: CpuSet) -> Self {
+        CpuSetUnchecked { cpus: cpu_set.cpus }
+    }
+}
+
+// The set operations return sorted CPUs without duplicates.
+fn __sorted(mut cpus: Vec<u32>) -> Vec<u32> {
+    cpus.sort_unstable();
+    cpus.dedup();
+    cpus
+}
+
+fn __union(left: &[u32], right: &[u32]) -> Vec<u32> {
+    __sorted(left.iter().chain(right.iter()).copied().collect())
+}
+
+fn __intersection(left: &[u32], right: &[u32]) -> Vec<u32> {
+    __sorted(left.iter().filter(|cpu| right.contains(cpu)).copied().collect())
+}
+
+fn __difference(left: &[u32], right: &[u32]) -> Vec<u32> {
+    __sorted(left.iter().filter(|cpu| !right.contains(cpu)).copied().collect())
+}
+
+fn __is_subset(left: &[u32], right: &[u32]) -> bool {
+    left.iter().all(|cpu| right.contains(cpu))
+}
+
+fn __cpus_to_hex_mask(cpus: &[u32]) -> String {
+    let max_cpu = cpus.iter().copied().max().unwrap_or(0);
+    let mut words = vec![0u32; max_cpu as usize / 32 + 1];
+    cpus.iter()
+        .for_each(|cpu| words[*cpu as usize / 32] |= 1 << (cpu % 32));
+
+    words.iter().rev()
+        .map(|word| format!("{word:08x}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// Accepts both comma separated 32-bit words and a single hex number of any
+// length, optionally prefixed by 0x.
+fn __cpus_from_hex_mask(mask: &str) -> Result<Vec<u32>, String> {
+    let mask = mask.trim_ascii();
+    let mask = mask.strip_prefix("0x").unwrap_or(mask);
+
+    let mut cpus = Vec::new();
+    for (word_idx, word) in mask.split(',').rev().enumerate() {
+        if word.is_empty() || (mask.contains(',') && word.len() > 8) {
+            return Err(format!("Invalid hex mask word '{word}' in {mask}"));
+        }
+
+        for (digit_idx, digit) in word.chars().rev().enumerate() {
+            let digit = digit.to_digit(16)
+                .ok_or_else(|| format!("Invalid hex digit '{digit}' in {mask}"))?;
+
+            (0..4).filter(|bit| digit & (1 << bit) != 0)
+                .for_each(|bit| cpus.push(word_idx as u32 * 32 + digit_idx as u32 * 4 + bit));
+        }
+    }
+
+    Ok(__sorted(cpus))
+}
+
+impl TryFrom<&CpuSet> for libc::cpu_set_t {
+    type Error = CpuSetBuildError;
+
+    fn try_from(cpu_set: &CpuSet) -> Result<Self, Self::Error> {
+        let mut out: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+
+        for cpu in cpu_set.iter() {
+            if cpu as usize >= libc::CPU_SETSIZE as usize {
+                return Err(CpuSetBuildError::UnavailableCPU(cpu));
+            }
+
+            unsafe { libc::CPU_SET(cpu as usize, &mut out); }
+        }
+
+        Ok(out)
+    }
+}
+
+impl From<&libc::cpu_set_t> for CpuSetUnchecked {
+    fn from(cpu_set: &libc::cpu_set_t) -> Self {
+        CpuSetUnchecked {
+            cpus: (0..libc::CPU_SETSIZE as u32)
+                .filter(|cpu| unsafe { libc::CPU_ISSET(*cpu as usize, cpu_set) })
+                .collect()
+        }
+    }
 }
 
 impl FromStr for CpuSet {
@@ -155,8 +557,14 @@ impl FromStr for CpuSetUnchecked {
             }
         );
 
+        // kernel files list no CPUs with an empty line
+        let s = s.trim_ascii();
+        if s.is_empty() {
+            return Ok(CpuSetUnchecked::empty());
+        }
+
         let separator_parser = map((tag(","), multispace0), |_| ());
-        let mut parser = map(
+        let mut parser = all_consuming(map(
             separated_list1(
                 separator_parser,
                 alt((range_parser, single_parser_pair))
@@ -171,10 +579,10 @@ impl FromStr for CpuSetUnchecked {
 
                 out
             }
-        );
+        ));
 
         Ok(CpuSetUnchecked {
-            cpus: parser.parse(s).map_err(|err| format!("{err}"))?.1
+            cpus: __sorted(parser.parse(s).map_err(|err| format!("{err}"))?.1)
         })
     }
 }
@@ -193,21 +601,30 @@ impl Into<Result<CpuSet, CpuSetBuildError>> for CpuSetUnchecked {
     }
 }
 
+// Canonical cpu list format, as in the kernel's cpulist files (e.g. 0-3,6),
+// which is accepted back by the parser.
 fn display_cpus(cpus: &[u32], f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-    write!(f, "[")?;
-
-    let mut iter = cpus.iter().peekable();
-    if iter.peek().is_some() {
-        let cpu = iter.next().unwrap();
-        write!(f, "{cpu}")?;
+    let cpus = __sorted(cpus.to_vec());
 
-        for cpu in iter {
-            write!(f, ", {cpu}")?;
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for cpu in cpus {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == cpu => *last = cpu,
+            _ => ranges.push((cpu, cpu)),
         }
     }
 
-    write!(f, "]")?;
-    Ok(())
+    let ranges: Vec<_> = ranges.into_iter()
+        .map(|(first, last)| {
+            if first == last {
+                format!("{first}")
+            } else {
+                format!("{first}-{last}")
+            }
+        })
+        .collect();
+
+    write!(f, "{}", ranges.join(","))
 }
 
 impl std::fmt::Display for CpuSet {
@@ -239,4 +656,36 @@ pub fn set_cpuset_to_pid(pid: u32, cpu_set: &CpuSet) -> Result<(), Box<dyn std::
     __println_debug(|| format!("Changed CPU affinity of pid {pid} to {cpu_set:?}"));
 
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn get_cpuset_of_pid(pid: u32) -> Result<CpuSet, Box<dyn std::error::Error>> {
+    let max_cpu = CpuSet::all()?.cpus.iter().copied().max().unwrap_or(0);
+    let affinity = scheduler::get_affinity(pid as i32, max_cpu as usize + 1)
+        .map_err(|_| format!("Error in getting affinity for pid {pid}"))?;
+
+    Ok(CpuSet {
+        cpus: (0..=max_cpu).filter(|cpu| affinity.is_set(*cpu as usize)).collect()
+    })
+}
+
+/// Online CPUs which can be taken offline, i.e. which expose
+/// /sys/devices/system/cpu/cpu<N>/online (usually not CPU 0).
+pub fn get_hotpluggable_cpus() -> Result<CpuSet, Box<dyn std::error::Error>> {
+    let cpus: Vec<_> = CpuSet::all()?.iter()
+        .filter(|cpu| std::fs::exists(format!("/sys/devices/system/cpu/cpu{cpu}/online")).unwrap_or(false))
+        .collect();
+
+    Ok(CpuSet { cpus })
+}
+
+pub fn set_cpu_online(cpu: u32, online: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let path = format!("/sys/devices/system/cpu/cpu{cpu}/online");
+    let value = if online { "1" } else { "0" };
+
+    std::fs::write(&path, value)
+        .map_err(|err| format!("Error in writing {value} to {path}: {err}"))?;
+
+    __println_debug(|| format!("CPU {cpu} {}", if online { "online" } else { "offline" }));
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::utils::__println_debug;
+
+pub mod prelude {
+    pub use super::{
+        get_fair_server_cpus,
+        get_fair_server_runtime_us,
+        get_fair_server_period_us,
+        set_fair_server_runtime_us,
+        set_fair_server_period_us,
+        fair_server_setup,
+        get_fair_server_bw,
+        get_fair_server_avg_bw,
+    };
+}
+
+// The kernel exposes the parameters of each CPU's fair server, in nanoseconds,
+// at <FAIR_SERVER_DIR>/cpu<N>/{runtime,period}. Requires DebugFS.
+const FAIR_SERVER_DIR: &str = "/sys/kernel/debug/sched/fair_server";
+
+fn __read_fair_server(cpu: u32, file: &str) -> Result<u64, Box<dyn std::error::Error>> {
+    let path = format!("{FAIR_SERVER_DIR}/cpu{cpu}/{file}");
+
+    Ok(std::fs::read_to_string(&path)
+        .map_err(|err| format!("Error in reading {path}: {err}"))?
+        .trim().parse::<u64>()
+        .map_err(|err| format!("Error in parsing {path}: {err}"))?)
+}
+
+fn __write_fair_server(cpu: u32, file: &str, value_ns: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let path = format!("{FAIR_SERVER_DIR}/cpu{cpu}/{file}");
+
+    std::fs::write(&path, format!("{value_ns}"))
+        .map_err(|err| format!("Error in writing {value_ns} ns to {path}: {err}"))?;
+
+    Ok(())
+}
+
+/// CPUs which have a fair server, in ascending order.
+pub fn get_fair_server_cpus() -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+    let mut cpus: Vec<_> = std::fs::read_dir(FAIR_SERVER_DIR)
+        .map_err(|err| format!("Error in reading directory {FAIR_SERVER_DIR}: {err}"))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|name| name.strip_prefix("cpu")?.parse::<u32>().ok())
+        .collect();
+
+    cpus.sort();
+    Ok(cpus)
+}
+
+pub fn get_fair_server_runtime_us(cpu: u32) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(__read_fair_server(cpu, "runtime")? / 1000)
+}
+
+pub fn get_fair_server_period_us(cpu: u32) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(__read_fair_server(cpu, "period")? / 1000)
+}
+
+pub fn set_fair_server_runtime_us(cpu: u32, runtime_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    __write_fair_server(cpu, "runtime", runtime_us * 1000)?;
+
+    __println_debug(|| format!("Set runtime {runtime_us} us to fair server of CPU {cpu}"));
+
+    Ok(())
+}
+
+pub fn set_fair_server_period_us(cpu: u32, period_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    __write_fair_server(cpu, "period", period_us * 1000)?;
+
+    __println_debug(|| format!("Set period {period_us} us to fair server of CPU {cpu}"));
+
+    Ok(())
+}
+
+/// Set both runtime and period of a CPU's fair server, in the order which
+/// keeps the reservation valid (runtime <= period) throughout the update.
+pub fn fair_server_setup(cpu: u32, runtime_us: u64, period_us: u64) -> Result<(), Box<dyn std::error::Error>> {
+    let old_period_us = get_fair_server_period_us(cpu)?;
+
+    if period_us > old_period_us {
+        set_fair_server_period_us(cpu, period_us)?;
+        set_fair_server_runtime_us(cpu, runtime_us)?;
+    } else {
+        set_fair_server_runtime_us(cpu, runtime_us)?;
+        set_fair_server_period_us(cpu, period_us)?;
+    }
+
+    __println_debug(|| format!("Fair server of CPU {cpu} setup to {runtime_us}/{period_us} reservation"));
+
+    Ok(())
+}
+
+pub fn get_fair_server_bw(cpu: u32) -> Result<f64, Box<dyn std::error::Error>> {
+    Ok(__read_fair_server(cpu, "runtime")? as f64 / __read_fair_server(cpu, "period")? as f64)
+}
+
+pub fn get_fair_server_avg_bw() -> Result<f64, Box<dyn std::error::Error>> {
+    let cpus = get_fair_server_cpus()?;
+    if cpus.is_empty() {
+        Err(format!("No fair servers found in {FAIR_SERVER_DIR}"))?;
+    }
+
+    let total_bw = cpus.iter()
+        .map(|cpu| get_fair_server_bw(*cpu))
+        .sum::<Result<f64, _>>()?;
+
+    Ok(total_bw / cpus.len() as f64)
+}
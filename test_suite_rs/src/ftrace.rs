@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+
+use crate::{cpuset::CpuSet, utils::{__println_debug, __shell}};
+
+pub mod prelude {
+    pub use super::{
+        mount_trace_fs,
+        Tracer,
+        TraceEvent,
+        ExecInterval,
+        BudgetViolation,
+        PriorityInversion,
+        parse_trace,
+        execution_intervals,
+        verify_budget,
+        verify_priority_order,
+    };
+}
+
+const TRACEFS_ROOT: &str = "/sys/kernel/tracing";
+
+const TRACED_EVENTS: [&str; 2] = ["sched/sched_switch", "sched/sched_wakeup"];
+
+// mount -t tracefs nodev /sys/kernel/tracing
+pub fn mount_trace_fs() -> Result<(), Box<dyn std::error::Error>> {
+    if !__shell("mount | grep tracefs")?.stdout.is_empty() {
+        __println_debug(|| "TraceFS already mounted".to_owned());
+        return Ok(());
+    }
+
+    if !__shell(&format!("mount -t tracefs nodev {TRACEFS_ROOT}"))?.status.success() {
+        __println_debug(|| "Error in mounting TraceFS".to_owned());
+        Err("Error in mounting TraceFS")?;
+    }
+
+    __println_debug(|| "Mounted TraceFS".to_owned());
+
+    Ok(())
+}
+
+fn __tracefs_write(file: &str, value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(format!("{TRACEFS_ROOT}/{file}"), value)
+        .map_err(|err| format!("Error in writing {value} to {TRACEFS_ROOT}/{file}: {err}"))?;
+
+    Ok(())
+}
+
+fn __tracefs_read(file: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(std::fs::read_to_string(format!("{TRACEFS_ROOT}/{file}"))
+        .map_err(|err| format!("Error in reading {TRACEFS_ROOT}/{file}: {err}"))?)
+}
+
+/// Guard over the kernel's tracing buffer, recording the scheduler's switch
+/// and wakeup events on the given CPUs. The tracing configuration is restored
+/// when the guard is dropped.
+pub struct Tracer {
+    cpumask_orig: String,
+    buffer_size_orig: String,
+}
+
+impl Tracer {
+    pub fn start(cpu_set: &CpuSet, buffer_size_kb: u64) -> Result<Tracer, Box<dyn std::error::Error>> {
+        mount_trace_fs()?;
+
+        let tracer = Tracer {
+            cpumask_orig: __tracefs_read("tracing_cpumask")?.trim().to_owned(),
+            buffer_size_orig: __tracefs_read("buffer_size_kb")?.trim().to_owned(),
+        };
+
+        __tracefs_write("tracing_on", "0")?;
+        __tracefs_write("current_tracer", "nop")?;
+        __tracefs_write("trace", "")?;
+        __tracefs_write("tracing_cpumask", &cpu_set.to_hex_mask())?;
+        __tracefs_write("buffer_size_kb", &format!("{buffer_size_kb}"))?;
+        TRACED_EVENTS.iter()
+            .try_for_each(|event| __tracefs_write(&format!("events/{event}/enable"), "1"))?;
+        __tracefs_write("tracing_on", "1")?;
+
+        __println_debug(|| format!("Started tracing on cpus {cpu_set}"));
+
+        Ok(tracer)
+    }
+
+    /// Stop tracing and parse the recorded events.
+    pub fn stop(self) -> Result<Vec<TraceEvent>, Box<dyn std::error::Error>> {
+        __tracefs_write("tracing_on", "0")?;
+        let data = __tracefs_read("trace")?;
+
+        __println_debug(|| "Stopped tracing".to_owned());
+
+        parse_trace(&data)
+    }
+
+    fn __restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        __tracefs_write("tracing_on", "0")?;
+        TRACED_EVENTS.iter()
+            .try_for_each(|event| __tracefs_write(&format!("events/{event}/enable"), "0"))?;
+        __tracefs_write("trace", "")?;
+        __tracefs_write("tracing_cpumask", &self.cpumask_orig)?;
+        __tracefs_write("buffer_size_kb", &self.buffer_size_orig)?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Tracer {
+    fn drop(&mut self) {
+        let _ = self.__restore();
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub enum TraceEvent {
+    Switch {
+        cpu: u32,
+        time_ns: u64,
+        prev_pid: u32,
+        prev_prio: i32,
+        prev_state: String,
+        next_pid: u32,
+        next_prio: i32,
+    },
+    Wakeup {
+        cpu: u32,
+        time_ns: u64,
+        pid: u32,
+        prio: i32,
+        target_cpu: u32,
+    },
+}
+
+impl TraceEvent {
+    pub fn time_ns(&self) -> u64 {
+        match self {
+            TraceEvent::Switch { time_ns, .. } => *time_ns,
+            TraceEvent::Wakeup { time_ns, .. } => *time_ns,
+        }
+    }
+}
+
+fn __field<'a>(fields: &'a str, key: &str) -> Option<&'a str> {
+    // keys are always preceded by a space, except for the first one
+    let start =
+        if fields.starts_with(&format!("{key}=")) {
+            key.len() + 1
+        } else {
+            fields.find(&format!(" {key}="))? + key.len() + 2
+        };
+
+    fields[start..].split_ascii_whitespace().next()
+}
+
+fn __parse_field<T: std::str::FromStr>(fields: &str, key: &str) -> Result<T, String> {
+    __field(fields, key)
+        .ok_or_else(|| format!("missing field {key}"))?
+        .parse::<T>()
+        .map_err(|_| format!("invalid field {key}"))
+}
+
+// seconds.microseconds timestamp, as printed by the default trace clock
+fn __parse_timestamp(timestamp: &str) -> Result<u64, String> {
+    let (secs, frac) = timestamp.split_once('.')
+        .ok_or_else(|| format!("invalid timestamp {timestamp}"))?;
+
+    let secs = secs.parse::<u64>().map_err(|_| format!("invalid timestamp {timestamp}"))?;
+    let frac_ns = format!("{frac:0<9}")[..9].parse::<u64>()
+        .map_err(|_| format!("invalid timestamp {timestamp}"))?;
+
+    Ok(secs * 1_000_000_000 + frac_ns)
+}
+
+fn __parse_trace_line(line: &str) -> Result<Option<TraceEvent>, String> {
+    let (event, header, fields) =
+        if let Some((header, fields)) = line.split_once(": sched_switch: ") {
+            ("sched_switch", header, fields)
+        } else if let Some((header, fields)) = line.split_once(": sched_wakeup: ") {
+            ("sched_wakeup", header, fields)
+        } else {
+            return Ok(None);
+        };
+
+    // header: <comm>-<pid> [<cpu>] <flags> <timestamp>
+    let mut tokens = header.split_ascii_whitespace().rev();
+    let time_ns = __parse_timestamp(tokens.next().ok_or("missing timestamp")?)?;
+    let cpu = tokens
+        .find_map(|token| token.strip_prefix('[')?.strip_suffix(']')?.parse::<u32>().ok())
+        .ok_or("missing cpu")?;
+
+    let event =
+        if event == "sched_switch" {
+            TraceEvent::Switch {
+                cpu,
+                time_ns,
+                prev_pid: __parse_field(fields, "prev_pid")?,
+                prev_prio: __parse_field(fields, "prev_prio")?,
+                prev_state: __parse_field(fields, "prev_state")?,
+                next_pid: __parse_field(fields, "next_pid")?,
+                next_prio: __parse_field(fields, "next_prio")?,
+            }
+        } else {
+            TraceEvent::Wakeup {
+                cpu,
+                time_ns,
+                pid: __parse_field(fields, "pid")?,
+                prio: __parse_field(fields, "prio")?,
+                target_cpu: __parse_field(fields, "target_cpu")?,
+            }
+        };
+
+    Ok(Some(event))
+}
+
+/// Parse the sched_switch and sched_wakeup events of the textual trace
+/// (`/sys/kernel/tracing/trace`). Other events and comments are ignored.
+pub fn parse_trace(data: &str) -> Result<Vec<TraceEvent>, Box<dyn std::error::Error>> {
+    let mut events = Vec::new();
+    for (num, line) in data.lines().enumerate() {
+        if line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        if let Some(event) = __parse_trace_line(line)
+            .map_err(|err| format!("Trace parser error at line {}: {err}", num + 1))?
+        {
+            events.push(event);
+        }
+    }
+
+    events.sort_by_key(|event| event.time_ns());
+
+    Ok(events)
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct ExecInterval {
+    pub pid: u32,
+    pub cpu: u32,
+    pub start_ns: u64,
+    pub end_ns: u64,
+}
+
+// kernel priorities below 100 belong to real-time tasks
+const MAX_RT_PRIO: i32 = 100;
+
+/// Rebuild the intervals in which the given tasks executed as real-time tasks.
+pub fn execution_intervals(events: &[TraceEvent], pids: &[u32]) -> Vec<ExecInterval> {
+    let mut running: HashMap<u32, (u32, u64)> = HashMap::new();
+    let mut intervals = Vec::new();
+
+    for event in events {
+        let TraceEvent::Switch { cpu, time_ns, prev_pid, next_pid, next_prio, .. } = event
+            else { continue; };
+
+        if let Some((pid, start_ns)) = running.remove(cpu) && pid == *prev_pid {
+            intervals.push(ExecInterval { pid, cpu: *cpu, start_ns, end_ns: *time_ns });
+        }
+
+        if pids.contains(next_pid) && *next_prio < MAX_RT_PRIO {
+            running.insert(*cpu, (*next_pid, *time_ns));
+        }
+    }
+
+    intervals
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct BudgetViolation {
+    pub cpu: u32,
+    pub window_start_ns: u64,
+    pub used_ns: u64,
+}
+
+impl std::fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cpu {} used {} us in the period starting at {:.6} s",
+            self.cpu, self.used_ns / 1000, self.window_start_ns as f64 / 1e9)
+    }
+}
+
+/// Check that in every period, the given execution intervals consume at most
+/// `runtime_ns + epsilon_ns` on each CPU.
+///
+/// The periods of each CPU's server are reconstructed from the trace: a new
+/// period starts with the first execution on the CPU, and whenever execution
+/// resumes after the current period has elapsed (i.e. on replenishment or on a
+/// wakeup past the server's deadline). Workloads must thus be started after
+/// tracing begins.
+///
+/// This is an approximation: the trace does not contain the server's actual
+/// replenishment instants, so the reconstructed periods are anchored at the
+/// first execution after each one. When a replenishment is served late, the
+/// windows drift from the server's periods, and part of the runtime of two
+/// consecutive periods may fall in the same window; `epsilon_ns` must absorb
+/// this drift as well as the tracing and scheduling latencies.
+pub fn verify_budget(intervals: &[ExecInterval], runtime_ns: u64, period_ns: u64, epsilon_ns: u64) -> Vec<BudgetViolation> {
+    let mut per_cpu: HashMap<u32, Vec<&ExecInterval>> = HashMap::new();
+    intervals.iter()
+        .for_each(|interval| per_cpu.entry(interval.cpu).or_default().push(interval));
+
+    let mut violations = Vec::new();
+    for (cpu, mut intervals) in per_cpu.into_iter() {
+        intervals.sort_by_key(|interval| interval.start_ns);
+
+        let mut window: Option<(u64, u64)> = None; // (start, used)
+        let mut close_window = |window_start_ns: u64, used_ns: u64| {
+            if used_ns > runtime_ns + epsilon_ns {
+                violations.push(BudgetViolation { cpu, window_start_ns, used_ns });
+            }
+        };
+
+        for interval in intervals {
+            let mut start_ns = interval.start_ns;
+            while start_ns < interval.end_ns {
+                let (window_start_ns, used_ns) = match window {
+                    Some((window_start_ns, used_ns)) if start_ns < window_start_ns + period_ns =>
+                        (window_start_ns, used_ns),
+                    // an interval crossing the end of the period starts the
+                    // next one right at the replenishment
+                    Some((window_start_ns, used_ns)) => {
+                        close_window(window_start_ns, used_ns);
+                        (start_ns, 0)
+                    },
+                    None => (start_ns, 0),
+                };
+
+                let end_ns = interval.end_ns.min(window_start_ns + period_ns);
+                window = Some((window_start_ns, used_ns + (end_ns - start_ns)));
+                start_ns = end_ns;
+            }
+        }
+
+        if let Some((window_start_ns, used_ns)) = window {
+            close_window(window_start_ns, used_ns);
+        }
+    }
+
+    violations.sort_by_key(|violation| (violation.cpu, violation.window_start_ns));
+    violations
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct PriorityInversion {
+    pub cpu: u32,
+    pub start_ns: u64,
+    pub duration_ns: u64,
+    pub running_pid: u32,
+    pub waiting_pid: u32,
+}
+
+impl std::fmt::Display for PriorityInversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cpu {} ran pid {} for {} us at {:.6} s while pid {} was runnable",
+            self.cpu, self.running_pid, self.duration_ns / 1000, self.start_ns as f64 / 1e9, self.waiting_pid)
+    }
+}
+
+/// Check that none of the given tasks, listed as (pid, priority), executes
+/// while a higher priority one is runnable on the same CPU for more than
+/// `epsilon_ns`.
+///
+/// A task is runnable from its wakeup until it is switched out in a sleeping
+/// state: preempted or throttled tasks (`prev_state` R) stay runnable. Tasks
+/// whose state is unknown at the start of the trace are assumed to be sleeping
+/// until their first event.
+pub fn verify_priority_order(events: &[TraceEvent], tasks: &[(u32, u32)], epsilon_ns: u64) -> Vec<PriorityInversion> {
+    let priority = |pid: &u32| tasks.iter()
+        .find_map(|(task, prio)| (task == pid).then_some(*prio));
+
+    let mut running: HashMap<u32, (u32, u64)> = HashMap::new(); // cpu -> (pid, since)
+    let mut waiting: HashMap<u32, (u32, u64)> = HashMap::new(); // pid -> (cpu, since)
+    let mut inversions: Vec<PriorityInversion> = Vec::new();
+
+    // account the time elapsed on the cpu since its last event
+    let mut check = |running: &mut HashMap<u32, (u32, u64)>, waiting: &HashMap<u32, (u32, u64)>, cpu: u32, now_ns: u64| {
+        let Some((running_pid, since_ns)) = running.get_mut(&cpu) else { return; };
+        let running_prio = priority(running_pid);
+
+        let first_waiting = waiting.iter()
+            .filter(|(pid, (task_cpu, _))| *task_cpu == cpu && priority(pid) > running_prio)
+            .map(|(pid, (_, waiting_ns))| (*pid, (*waiting_ns + epsilon_ns).max(*since_ns)))
+            .min_by_key(|(_, start_ns)| *start_ns);
+
+        if let Some((waiting_pid, start_ns)) = first_waiting && start_ns < now_ns {
+            match inversions.last_mut() {
+                Some(last) if last.cpu == cpu && last.running_pid == *running_pid
+                    && last.start_ns + last.duration_ns == start_ns =>
+                    last.duration_ns = now_ns - last.start_ns,
+                _ =>
+                    inversions.push(PriorityInversion { cpu, start_ns, duration_ns: now_ns - start_ns, running_pid: *running_pid, waiting_pid }),
+            }
+        }
+
+        *since_ns = now_ns;
+    };
+
+    for event in events {
+        match event {
+            TraceEvent::Wakeup { time_ns, pid, target_cpu, .. } => {
+                if priority(pid).is_none() {
+                    continue;
+                }
+
+                check(&mut running, &waiting, *target_cpu, *time_ns);
+                if running.get(target_cpu).is_none_or(|(running_pid, _)| running_pid != pid) {
+                    waiting.entry(*pid).or_insert((*target_cpu, *time_ns));
+                }
+            },
+            TraceEvent::Switch { cpu, time_ns, prev_pid, prev_state, next_pid, .. } => {
+                check(&mut running, &waiting, *cpu, *time_ns);
+                running.remove(cpu);
+
+                if priority(prev_pid).is_some() && prev_state.starts_with('R') {
+                    waiting.insert(*prev_pid, (*cpu, *time_ns));
+                } else {
+                    waiting.remove(prev_pid);
+                }
+
+                if priority(next_pid).is_some() {
+                    waiting.remove(next_pid);
+                    running.insert(*cpu, (*next_pid, *time_ns));
+                }
+            },
+        }
+    }
+
+    inversions
+}
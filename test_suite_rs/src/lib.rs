@@ -6,6 +6,12 @@ pub mod cgroup;
 pub mod process;
 pub mod utils;
 pub mod cpuset;
+pub mod stats;
+pub mod report;
+pub mod ftrace;
+pub mod sched_debug;
+pub mod fair_server;
+pub mod sched_features;
 pub mod tests;
 
 pub mod prelude {
@@ -13,9 +19,16 @@ pub mod prelude {
     pub use super::process::prelude::*;
     pub use super::utils::prelude::*;
     pub use super::cpuset::prelude::*;
+    pub use super::stats::prelude::*;
+    pub use super::report::prelude::*;
+    pub use super::ftrace::prelude::*;
+    pub use super::sched_debug::prelude::*;
+    pub use super::fair_server::prelude::*;
+    pub use super::sched_features::prelude::*;
 
     pub use super::{
         MyProcess,
+        SUITE_MARKER_ENV,
         run_yes,
         cpu_hog,
         PeriodicTaskData,
@@ -48,6 +61,10 @@ impl DerefMut for MyProcess {
     }
 }
 
+/// Environment variable set on the processes started by the test suite, used
+/// by `tools reset` to recognize the leftover ones.
+pub const SUITE_MARKER_ENV: &str = "HCBS_TEST_SUITE";
+
 pub fn cpu_hog() -> Result<MyProcess, Box<dyn std::error::Error>> {
     use std::process::*;
 
@@ -55,6 +72,7 @@ pub fn cpu_hog() -> Result<MyProcess, Box<dyn std::error::Error>> {
 
     let proc = Command::new(cmd)
         .arg("hog")
+        .env(SUITE_MARKER_ENV, "1")
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -67,6 +85,7 @@ pub fn run_yes() -> Result<MyProcess, std::io::Error> {
     use std::process::*;
 
     let proc = Command::new("yes")
+        .env(SUITE_MARKER_ENV, "1")
         .stdin(Stdio::null())
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -146,6 +165,7 @@ pub fn run_periodic_thread(args: PeriodicThreadData) -> Result<MyProcess, Box<dy
 
     let proc = Command::new(cmd)
         .args(cmd_str)
+        .env(SUITE_MARKER_ENV, "1")
         .stdin(Stdio::null())
         .stdout(out_file)
         .stderr(Stdio::null())
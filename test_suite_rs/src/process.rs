@@ -10,6 +10,12 @@ pub mod prelude {
         kill,
         get_process_total_runtime_usage,
         get_process_total_cpu_usage,
+        get_process_runtime_ns,
+        ForkedProcess,
+        fork_process,
+        SharedMemory,
+        now_ns,
+        busy_cpu_time,
     };
 }
 
@@ -115,4 +121,133 @@ pub fn get_process_total_cpu_usage(pid: u32) -> Result<f64, String> {
 
     let elapsed = uptime - start_time;
     Ok((utime + stime)/ elapsed)
-}
\ No newline at end of file
+}
+
+pub fn get_process_runtime_ns(pid: u32) -> Result<u64, String> {
+    std::fs::read_to_string(format!("/proc/{pid}/schedstat"))
+        .map_err(|err| format!("{err:?}"))?
+        .split_whitespace().next().ok_or("Error in reading /proc/<pid>/schedstat".to_owned())?
+        .parse::<u64>().map_err(|err| format!("{err:?}"))
+}
+
+/// A forked copy of the calling process, killed and reaped on drop.
+pub struct ForkedProcess {
+    pid: u32,
+    reaped: bool,
+}
+
+impl ForkedProcess {
+    pub fn id(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn kill(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.reaped { return Ok(()); }
+
+        if unsafe { libc::kill(self.pid as libc::pid_t, libc::SIGKILL) } != 0 {
+            Err(format!("Error in killing forked process {}: {}", self.pid, std::io::Error::last_os_error()))?;
+        }
+
+        self.wait().map(|_| ())
+    }
+
+    /// Wait for the process to terminate, returning its exit code, or None if
+    /// it was killed by a signal.
+    pub fn wait(&mut self) -> Result<Option<i32>, Box<dyn std::error::Error>> {
+        let mut status = 0;
+        if unsafe { libc::waitpid(self.pid as libc::pid_t, &mut status, 0) } < 0 {
+            Err(format!("Error in waiting forked process {}: {}", self.pid, std::io::Error::last_os_error()))?;
+        }
+
+        self.reaped = true;
+        __println_debug(|| format!("Reaped forked process {}", self.pid));
+
+        Ok(libc::WIFEXITED(status).then(|| libc::WEXITSTATUS(status)))
+    }
+}
+
+impl Drop for ForkedProcess {
+    fn drop(&mut self) {
+        let _ = self.kill();
+    }
+}
+
+/// Fork the calling process, with the child running the given function and
+/// exiting with its return value.
+///
+/// The child must stick to async-signal-safe operations (e.g. atomics,
+/// syscalls, no allocations), as the other threads of the parent do not exist
+/// in it.
+pub fn fork_process<F: FnOnce() -> i32>(fun: F) -> Result<ForkedProcess, Box<dyn std::error::Error>> {
+    match unsafe { libc::fork() } {
+        -1 => Err(format!("Error in forking: {}", std::io::Error::last_os_error()))?,
+        0 => {
+            // a panic must not unwind into the copy of the parent's stack,
+            // running its destructors (e.g. of cgroups) in the child
+            let code = std::panic::catch_unwind(std::panic::AssertUnwindSafe(fun))
+                .unwrap_or(101);
+
+            unsafe { libc::_exit(code) }
+        },
+        pid => {
+            __println_debug(|| format!("Forked process {pid}"));
+            Ok(ForkedProcess { pid: pid as u32, reaped: false })
+        },
+    }
+}
+
+/// A value in an anonymous shared mapping, visible to (and modifiable by) the
+/// processes forked after its creation.
+pub struct SharedMemory<T> {
+    ptr: *mut T,
+}
+
+impl<T> SharedMemory<T> {
+    pub fn new(value: T) -> Result<Self, Box<dyn std::error::Error>> {
+        let ptr = unsafe {
+            libc::mmap(std::ptr::null_mut(), size_of::<T>(),
+                libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED | libc::MAP_ANONYMOUS, -1, 0)
+        };
+
+        if ptr == libc::MAP_FAILED {
+            Err(format!("Error in mapping shared memory: {}", std::io::Error::last_os_error()))?;
+        }
+
+        let ptr = ptr as *mut T;
+        unsafe { ptr.write(value); }
+
+        Ok(Self { ptr })
+    }
+}
+
+impl<T> std::ops::Deref for SharedMemory<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> Drop for SharedMemory<T> {
+    fn drop(&mut self) {
+        unsafe {
+            self.ptr.drop_in_place();
+            libc::munmap(self.ptr as *mut libc::c_void, size_of::<T>());
+        }
+    }
+}
+
+/// Current time of the given clock, in nanoseconds. Async-signal-safe.
+pub fn now_ns(clock: libc::clockid_t) -> u64 {
+    let mut time = libc::timespec { tv_sec: 0, tv_nsec: 0 };
+    unsafe { libc::clock_gettime(clock, &mut time); }
+
+    time.tv_sec as u64 * 1_000_000_000 + time.tv_nsec as u64
+}
+
+/// Busy loop until the calling thread has consumed the given cpu time.
+/// Async-signal-safe.
+pub fn busy_cpu_time(ns: u64) {
+    let start = now_ns(libc::CLOCK_THREAD_CPUTIME_ID);
+    while now_ns(libc::CLOCK_THREAD_CPUTIME_ID) - start < ns {}
+}
@@ -4,7 +4,9 @@ pub mod prelude {
     pub use super::{
         SchedPolicy,
         SchedPolicyError,
+        SchedFlag,
         set_scheduler,
+        set_scheduler_with_flags,
         get_scheduler,
     };
 }
@@ -29,6 +31,65 @@ impl SchedPolicy {
     pub fn other() -> Self { SchedPolicy::OTHER { nice: 0 }}
 }
 
+/// Parse a policy in the form `other[:nice]`, `batch[:nice]`, `idle`,
+/// `fifo:prio`, `rr:prio` or `deadline:runtime/deadline/period` (in ms).
+impl std::str::FromStr for SchedPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, params) = match s.split_once(':') {
+            Some((name, params)) => (name, Some(params)),
+            None => (s, None),
+        };
+
+        let parse_i32 = |param: Option<&str>, default: Option<i32>| {
+            match (param, default) {
+                (Some(param), _) => param.parse::<i32>()
+                    .map_err(|err| format!("Invalid parameter {param} for policy {name}: {err}")),
+                (None, Some(default)) => Ok(default),
+                (None, None) => Err(format!("Missing priority for policy {name}")),
+            }
+        };
+
+        match name.to_ascii_lowercase().as_str() {
+            "other" => Ok(SchedPolicy::OTHER { nice: parse_i32(params, Some(0))? }),
+            "batch" => Ok(SchedPolicy::BATCH { nice: parse_i32(params, Some(0))? }),
+            "idle" => Ok(SchedPolicy::IDLE),
+            "fifo" => Ok(SchedPolicy::FIFO(parse_i32(params, None)?)),
+            "rr" => Ok(SchedPolicy::RR(parse_i32(params, None)?)),
+            "deadline" => {
+                let params: Vec<_> = params
+                    .ok_or_else(|| format!("Missing runtime/deadline/period for policy {name}"))?
+                    .split('/')
+                    .map(|param| param.parse::<u64>()
+                        .map_err(|err| format!("Invalid parameter {param} for policy {name}: {err}")))
+                    .try_collect()?;
+
+                let [runtime_ms, deadline_ms, period_ms] = params[..] else {
+                    return Err(format!("Expected runtime/deadline/period for policy {name}"));
+                };
+
+                Ok(SchedPolicy::DEADLINE { runtime_ms, deadline_ms, period_ms })
+            },
+            _ => Err(format!("Unknown policy {name}")),
+        }
+    }
+}
+
+impl std::fmt::Display for SchedPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedPolicy::OTHER { nice } => write!(f, "OTHER(nice {nice})"),
+            SchedPolicy::BATCH { nice } => write!(f, "BATCH(nice {nice})"),
+            SchedPolicy::IDLE => write!(f, "IDLE"),
+            SchedPolicy::FIFO(prio) => write!(f, "FIFO({prio})"),
+            SchedPolicy::RR(prio) => write!(f, "RR({prio})"),
+            SchedPolicy::DEADLINE { runtime_ms, deadline_ms, period_ms }
+                => write!(f, "DEADLINE(runtime {runtime_ms}, deadline {deadline_ms}, period {period_ms})"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SchedPolicyError {
     SyscallError(std::io::Error),
@@ -51,6 +112,27 @@ impl std::fmt::Display for SchedPolicyError {
 
 impl std::error::Error for SchedPolicyError {}
 
+/// Flags of sched_setattr(2).
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(clap::ValueEnum)]
+pub enum SchedFlag {
+    ResetOnFork,
+    Reclaim,
+    DlOverrun,
+}
+
+impl SchedFlag {
+    fn bits(self) -> u64 {
+        (match self {
+            SchedFlag::ResetOnFork => libc::SCHED_FLAG_RESET_ON_FORK,
+            SchedFlag::Reclaim => libc::SCHED_FLAG_RECLAIM,
+            SchedFlag::DlOverrun => libc::SCHED_FLAG_DL_OVERRUN,
+        }) as u64
+    }
+}
+
 impl TryFrom<sched_attr> for SchedPolicy {
     type Error = SchedPolicyError;
 
@@ -157,9 +239,20 @@ pub fn get_scheduler(pid: u32) -> Result<SchedPolicy, SchedPolicyError> {
 }
 
 pub fn set_scheduler(pid: u32, policy: SchedPolicy) -> Result<(), SchedPolicyError> {
-    unsafe {
-        let attr: sched_attr = policy.into();
+    __set_sched_attr(pid, policy.into())
+}
+
+/// Like set_scheduler, but the given flags replace the default ones.
+pub fn set_scheduler_with_flags(pid: u32, policy: SchedPolicy, flags: &[SchedFlag]) -> Result<(), SchedPolicyError> {
+    let mut attr: sched_attr = policy.into();
+    attr.sched_flags = flags.iter()
+        .fold(0, |sched_flags, flag| sched_flags | flag.bits());
 
+    __set_sched_attr(pid, attr)
+}
+
+fn __set_sched_attr(pid: u32, attr: sched_attr) -> Result<(), SchedPolicyError> {
+    unsafe {
         let res =
             syscall(
                 SYS_sched_setattr,
@@ -174,4 +267,4 @@ pub fn set_scheduler(pid: u32, policy: SchedPolicy) -> Result<(), SchedPolicyErr
             Ok(())
         }
     }
-}
\ No newline at end of file
+}
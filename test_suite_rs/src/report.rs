@@ -0,0 +1,149 @@
+use std::{collections::BTreeMap, io::Write, sync::Mutex};
+
+use crate::utils::is_env_var_set;
+
+pub mod prelude {
+    pub use super::{
+        TestRecord,
+        TestOutcome,
+        report_metric,
+        report_expected_metric,
+        read_test_records,
+    };
+}
+
+// When set, every batch test result is appended as a JSON line to the file
+// named by this environment variable.
+const REPORT_ENV_VAR: &str = "BATCH_TEST_JSON";
+
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestOutcome {
+    Success,
+    Failure,
+    Skipped,
+    Inconclusive,
+}
+
+impl std::fmt::Display for TestOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TestOutcome::Success => write!(f, "success"),
+            TestOutcome::Failure => write!(f, "failure"),
+            TestOutcome::Skipped => write!(f, "skipped"),
+            TestOutcome::Inconclusive => write!(f, "inconclusive"),
+        }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct TestRecord {
+    pub category: String,
+    pub name: String,
+    pub outcome: TestOutcome,
+    #[serde(default)]
+    pub details: Option<String>,
+    /// Named measurements of the test, e.g. the mean bandwidth of a group of
+    /// processes.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>,
+    /// Expected values of the measurements that have one.
+    #[serde(default)]
+    pub expected: BTreeMap<String, f64>,
+}
+
+struct PendingRecord {
+    category: String,
+    name: String,
+    metrics: BTreeMap<String, f64>,
+    expected: BTreeMap<String, f64>,
+}
+
+static CURRENT_TEST: Mutex<Option<PendingRecord>> = Mutex::new(None);
+
+pub(crate) fn report_start(test_category: &str, test_name: &str) {
+    *CURRENT_TEST.lock().unwrap() = Some(PendingRecord {
+        category: test_category.to_owned(),
+        name: test_name.to_owned(),
+        metrics: BTreeMap::new(),
+        expected: BTreeMap::new(),
+    });
+}
+
+/// Attach a measurement to the running test's record.
+pub fn report_metric(name: &str, value: f64) {
+    if let Some(test) = CURRENT_TEST.lock().unwrap().as_mut() {
+        test.metrics.insert(name.to_owned(), value);
+    }
+}
+
+/// Attach a measurement and the value it was expected to have to the running
+/// test's record.
+pub fn report_expected_metric(name: &str, value: f64, expected: f64) {
+    if let Some(test) = CURRENT_TEST.lock().unwrap().as_mut() {
+        test.metrics.insert(name.to_owned(), value);
+        test.expected.insert(name.to_owned(), expected);
+    }
+}
+
+pub(crate) fn report_outcome(outcome: TestOutcome, details: Option<String>) {
+    let Some(test) = CURRENT_TEST.lock().unwrap().take() else { return; };
+
+    if !is_env_var_set(REPORT_ENV_VAR) {
+        return;
+    }
+
+    let record = TestRecord {
+        category: test.category,
+        name: test.name,
+        outcome,
+        details,
+        metrics: test.metrics,
+        expected: test.expected,
+    };
+
+    // reporting is best effort: do not make the test itself fail
+    let _ = write_test_record(&record)
+        .map_err(|err| eprintln!("Error in reporting test result: {err}"));
+}
+
+fn write_test_record(record: &TestRecord) -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::var(REPORT_ENV_VAR)?;
+
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+
+    std::fs::OpenOptions::new().create(true).append(true)
+        .open(&path)
+        .map_err(|err| format!("Error in opening report file {path}: {err}"))?
+        .write_all(line.as_bytes())
+        .map_err(|err| format!("Error in writing report file {path}: {err}"))?;
+
+    Ok(())
+}
+
+/// Read the records of a report file. Later records of the same test override
+/// the earlier ones.
+pub fn read_test_records(path: &str) -> Result<BTreeMap<(String, String), TestRecord>, Box<dyn std::error::Error>> {
+    let data = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read report file {path}: {err}"))?;
+
+    let mut records = BTreeMap::new();
+    for (num, line) in data.lines().enumerate() {
+        if line.trim_ascii().is_empty() {
+            continue;
+        }
+
+        let record: TestRecord = serde_json::from_str(line)
+            .map_err(|err| format!("Report file {path}, line {}: {err}", num + 1))?;
+
+        records.insert((record.category.clone(), record.name.clone()), record);
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,424 @@
+use std::collections::BTreeMap;
+
+pub mod prelude {
+    pub use super::{
+        SchedDebug,
+        CpuSchedDebug,
+        RqKind,
+        RqSection,
+        SchedDebugDiff,
+        FieldChange,
+    };
+}
+
+const SCHED_DEBUG_FILE: &str = "/sys/kernel/debug/sched/debug";
+
+/// Kind of a per-CPU runqueue section of sched/debug.
+#[derive(Debug)]
+#[derive(Clone, Copy)]
+#[derive(PartialEq, Eq)]
+pub enum RqKind {
+    Cfs,
+    Rt,
+    Dl,
+}
+
+impl std::fmt::Display for RqKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RqKind::Cfs => write!(f, "cfs_rq"),
+            RqKind::Rt => write!(f, "rt_rq"),
+            RqKind::Dl => write!(f, "dl_rq"),
+        }
+    }
+}
+
+/// A `cfs_rq[cpu]:/group`, `rt_rq[cpu]:/group` or `dl_rq[cpu]:` section.
+///
+/// The fields are stored by name without the leading dot, e.g. `rt_throttled`
+/// or `dl_bw->total_bw`, and their value is kept as printed by the kernel.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct RqSection {
+    pub kind: RqKind,
+    pub cpu: u32,
+    /// Task group path, `/` for the root group (empty for dl_rq sections).
+    pub group: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+impl RqSection {
+    pub fn name(&self) -> String {
+        format!("{}[{}]:{}", self.kind, self.cpu, self.group)
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(|value| value.as_str())
+    }
+
+    pub fn get_f64(&self, field: &str) -> Option<f64> {
+        __parse_f64(self.get(field)?)
+    }
+
+    pub fn get_u64(&self, field: &str) -> Option<u64> {
+        self.get(field)?.parse().ok()
+    }
+
+    /// Number of runnable entities of the runqueue's class.
+    pub fn nr_running(&self) -> Option<u64> {
+        match self.kind {
+            RqKind::Cfs => self.get_u64("nr_running")
+                .or_else(|| self.get_u64("h_nr_running")),
+            RqKind::Rt => self.get_u64("rt_nr_running"),
+            RqKind::Dl => self.get_u64("dl_nr_running"),
+        }
+    }
+
+    /// Whether the rt_rq is throttled (rt_rq only).
+    pub fn rt_throttled(&self) -> Option<bool> {
+        self.get_u64("rt_throttled").map(|throttled| throttled != 0)
+    }
+
+    /// Runtime consumed in the current period, in milliseconds (rt_rq only).
+    pub fn rt_time_ms(&self) -> Option<f64> {
+        self.get_f64("rt_time")
+    }
+
+    /// Runtime available in each period, in milliseconds (rt_rq only).
+    pub fn rt_runtime_ms(&self) -> Option<f64> {
+        self.get_f64("rt_runtime")
+    }
+
+    /// Maximum DEADLINE bandwidth of the root domain, in units of 2^-20 (dl_rq only).
+    pub fn dl_bw(&self) -> Option<u64> {
+        self.get_u64("dl_bw->bw")
+    }
+
+    /// Allocated DEADLINE bandwidth of the root domain, in units of 2^-20 (dl_rq only).
+    pub fn dl_total_bw(&self) -> Option<u64> {
+        self.get_u64("dl_bw->total_bw")
+    }
+}
+
+/// The `cpu#N` section of sched/debug along with its runqueues.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct CpuSchedDebug {
+    pub cpu: u32,
+    pub fields: BTreeMap<String, String>,
+    /// cfs_rqs keyed by task group path
+    pub cfs_rqs: BTreeMap<String, RqSection>,
+    /// rt_rqs keyed by task group path
+    pub rt_rqs: BTreeMap<String, RqSection>,
+    pub dl_rq: Option<RqSection>,
+}
+
+impl CpuSchedDebug {
+    fn new(cpu: u32) -> Self {
+        Self {
+            cpu,
+            fields: BTreeMap::new(),
+            cfs_rqs: BTreeMap::new(),
+            rt_rqs: BTreeMap::new(),
+            dl_rq: None,
+        }
+    }
+
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields.get(field).map(|value| value.as_str())
+    }
+
+    pub fn get_u64(&self, field: &str) -> Option<u64> {
+        self.get(field)?.parse().ok()
+    }
+
+    /// Number of runnable tasks on the CPU, of any class.
+    pub fn nr_running(&self) -> Option<u64> {
+        self.get_u64("nr_running")
+    }
+
+    pub fn cfs_rq(&self, group: &str) -> Option<&RqSection> {
+        self.cfs_rqs.get(&__group_path(group))
+    }
+
+    pub fn rt_rq(&self, group: &str) -> Option<&RqSection> {
+        self.rt_rqs.get(&__group_path(group))
+    }
+}
+
+/// Parsed snapshot of `/sys/kernel/debug/sched/debug`.
+///
+/// The runnable tasks tables are not parsed. Requires DebugFS to be mounted.
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SchedDebug {
+    pub version: String,
+    /// Top level fields, e.g. `ktime` and `jiffies`
+    pub globals: BTreeMap<String, String>,
+    /// The `sysctl_sched` section
+    pub sysctl: BTreeMap<String, String>,
+    pub cpus: BTreeMap<u32, CpuSchedDebug>,
+}
+
+enum Section {
+    Globals,
+    Sysctl,
+    Cpu(u32),
+    Rq(RqKind, u32, String),
+    Ignored,
+}
+
+impl SchedDebug {
+    pub fn snapshot() -> Result<Self, Box<dyn std::error::Error>> {
+        let data = std::fs::read_to_string(SCHED_DEBUG_FILE)
+            .map_err(|err| format!("Error in reading {SCHED_DEBUG_FILE} (is DebugFS mounted?): {err}"))?;
+
+        Self::parse(&data)
+    }
+
+    pub fn parse(data: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut sched_debug = Self {
+            version: String::new(),
+            globals: BTreeMap::new(),
+            sysctl: BTreeMap::new(),
+            cpus: BTreeMap::new(),
+        };
+
+        let mut section = Section::Globals;
+        for (num, line) in data.lines().enumerate() {
+            let trimmed = line.trim_ascii();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            if let Some(version) = trimmed.strip_prefix("Sched Debug Version:") {
+                sched_debug.version = version.trim_ascii().to_owned();
+            } else if trimmed == "sysctl_sched" {
+                section = Section::Sysctl;
+            } else if trimmed.starts_with("runnable tasks:") {
+                section = Section::Ignored;
+            } else if let Some(header) = trimmed.strip_prefix("cpu#") {
+                let cpu = header.split(',').next().unwrap_or(header).trim_ascii()
+                    .parse::<u32>()
+                    .map_err(|err| format!("sched/debug, line {}: invalid cpu header: {err}", num + 1))?;
+
+                sched_debug.cpus.insert(cpu, CpuSchedDebug::new(cpu));
+                section = Section::Cpu(cpu);
+            } else if let Some((kind, cpu, group)) = __parse_rq_header(trimmed) {
+                let cpu = cpu
+                    .map_err(|err| format!("sched/debug, line {}: invalid runqueue header: {err}", num + 1))?;
+
+                let rq = RqSection { kind, cpu, group: group.clone(), fields: BTreeMap::new() };
+                let cpu_debug = sched_debug.cpus.entry(cpu)
+                    .or_insert_with(|| CpuSchedDebug::new(cpu));
+
+                match kind {
+                    RqKind::Cfs => { cpu_debug.cfs_rqs.insert(group.clone(), rq); },
+                    RqKind::Rt => { cpu_debug.rt_rqs.insert(group.clone(), rq); },
+                    RqKind::Dl => { cpu_debug.dl_rq = Some(rq); },
+                };
+
+                section = Section::Rq(kind, cpu, group);
+            } else if let Some((key, value)) = trimmed.split_once(':') {
+                let key = key.trim_ascii();
+                let key = key.strip_prefix('.').unwrap_or(key).to_owned();
+                let value = value.trim_ascii().to_owned();
+
+                let fields = match &section {
+                    Section::Globals => &mut sched_debug.globals,
+                    Section::Sysctl => &mut sched_debug.sysctl,
+                    Section::Cpu(cpu) => &mut sched_debug.cpus.get_mut(cpu).unwrap().fields,
+                    Section::Rq(kind, cpu, group) => {
+                        let cpu_debug = sched_debug.cpus.get_mut(cpu).unwrap();
+                        let rq = match kind {
+                            RqKind::Cfs => cpu_debug.cfs_rqs.get_mut(group),
+                            RqKind::Rt => cpu_debug.rt_rqs.get_mut(group),
+                            RqKind::Dl => cpu_debug.dl_rq.as_mut(),
+                        };
+
+                        &mut rq.unwrap().fields
+                    },
+                    Section::Ignored => continue,
+                };
+
+                fields.insert(key, value);
+            }
+        }
+
+        if sched_debug.cpus.is_empty() {
+            Err("sched/debug: no cpu sections found")?;
+        }
+
+        Ok(sched_debug)
+    }
+
+    /// The rt_rqs of the given task group on every CPU. The group can be given
+    /// either as a cgroup name (e.g. `g0`) or as a path (e.g. `/g0`).
+    pub fn rt_rqs(&self, group: &str) -> Vec<&RqSection> {
+        self.cpus.values()
+            .filter_map(|cpu| cpu.rt_rq(group))
+            .collect()
+    }
+
+    /// The cfs_rqs of the given task group on every CPU.
+    pub fn cfs_rqs(&self, group: &str) -> Vec<&RqSection> {
+        self.cpus.values()
+            .filter_map(|cpu| cpu.cfs_rq(group))
+            .collect()
+    }
+
+    pub fn dl_rqs(&self) -> Vec<&RqSection> {
+        self.cpus.values()
+            .filter_map(|cpu| cpu.dl_rq.as_ref())
+            .collect()
+    }
+
+    fn sections(&self) -> BTreeMap<String, &BTreeMap<String, String>> {
+        let mut sections = BTreeMap::new();
+        sections.insert(String::new(), &self.globals);
+        sections.insert("sysctl_sched".to_owned(), &self.sysctl);
+
+        for cpu in self.cpus.values() {
+            sections.insert(format!("cpu#{}", cpu.cpu), &cpu.fields);
+            cpu.cfs_rqs.values()
+                .chain(cpu.rt_rqs.values())
+                .chain(cpu.dl_rq.iter())
+                .for_each(|rq| { sections.insert(rq.name(), &rq.fields); });
+        }
+
+        sections
+    }
+
+    /// Field by field changes from this snapshot to a later one. Sections and
+    /// fields appearing in only one of the snapshots are reported as well.
+    pub fn diff(&self, later: &SchedDebug) -> SchedDebugDiff {
+        let old_sections = self.sections();
+        let new_sections = later.sections();
+        let empty = BTreeMap::new();
+
+        let mut section_names: Vec<_> = old_sections.keys().chain(new_sections.keys()).collect();
+        section_names.sort();
+        section_names.dedup();
+
+        let mut changes = Vec::new();
+        for section in section_names {
+            let old_fields = old_sections.get(section).copied().unwrap_or(&empty);
+            let new_fields = new_sections.get(section).copied().unwrap_or(&empty);
+
+            let mut field_names: Vec<_> = old_fields.keys().chain(new_fields.keys()).collect();
+            field_names.sort();
+            field_names.dedup();
+
+            for field in field_names {
+                let old = old_fields.get(field);
+                let new = new_fields.get(field);
+                if old == new {
+                    continue;
+                }
+
+                changes.push(FieldChange {
+                    section: section.clone(),
+                    field: field.clone(),
+                    old: old.cloned(),
+                    new: new.cloned(),
+                });
+            }
+        }
+
+        SchedDebugDiff { changes }
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct FieldChange {
+    /// Section name as printed by the kernel, e.g. `rt_rq[0]:/g0`; empty for
+    /// the top level fields
+    pub section: String,
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl FieldChange {
+    /// Numeric difference of the field's values, if both are numbers.
+    pub fn delta(&self) -> Option<f64> {
+        Some(__parse_f64(self.new.as_ref()?)? - __parse_f64(self.old.as_ref()?)?)
+    }
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let old = self.old.as_deref().unwrap_or("<none>");
+        let new = self.new.as_deref().unwrap_or("<none>");
+
+        if self.section.is_empty() {
+            write!(f, "{}: {old} -> {new}", self.field)?;
+        } else {
+            write!(f, "{}.{}: {old} -> {new}", self.section, self.field)?;
+        }
+        if let Some(delta) = self.delta() {
+            write!(f, " ({delta:+})")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct SchedDebugDiff {
+    pub changes: Vec<FieldChange>,
+}
+
+impl SchedDebugDiff {
+    /// Changes of the given field in any section.
+    pub fn field<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a FieldChange> {
+        self.changes.iter().filter(move |change| change.field == field)
+    }
+
+    /// Changes of the sections whose name starts with the given prefix, e.g.
+    /// `rt_rq[` for all the rt_rqs or `cpu#1` for a single CPU.
+    pub fn sections<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a FieldChange> {
+        self.changes.iter().filter(move |change| change.section.starts_with(prefix))
+    }
+}
+
+impl std::fmt::Display for SchedDebugDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for change in self.changes.iter() {
+            writeln!(f, "{change}")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn __parse_rq_header(line: &str) -> Option<(RqKind, Result<u32, std::num::ParseIntError>, String)> {
+    let (kind, rest) =
+        if let Some(rest) = line.strip_prefix("cfs_rq[") {
+            (RqKind::Cfs, rest)
+        } else if let Some(rest) = line.strip_prefix("rt_rq[") {
+            (RqKind::Rt, rest)
+        } else {
+            (RqKind::Dl, line.strip_prefix("dl_rq[")?)
+        };
+
+    let (cpu, group) = rest.split_once("]:")?;
+
+    Some((kind, cpu.parse::<u32>(), group.trim_ascii().to_owned()))
+}
+
+fn __group_path(group: &str) -> String {
+    match group {
+        "" | "." | "/" => "/".to_owned(),
+        _ if group.starts_with('/') => group.to_owned(),
+        _ => format!("/{group}"),
+    }
+}
+
+// values are printed either as integers, as fixed point numbers or followed by
+// a description, e.g. "1 (logarithmic)"
+fn __parse_f64(value: &str) -> Option<f64> {
+    value.split_ascii_whitespace().next()?.parse().ok()
+}
@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+
+use crate::utils::__println_debug;
+
+pub mod prelude {
+    pub use super::{
+        SchedFeature,
+        SchedFeaturesGuard,
+        get_sched_features,
+        is_sched_feature_enabled,
+        set_sched_feature,
+        sched_feature_matrix,
+    };
+}
+
+// Requires DebugFS. Reading the file lists every feature, prefixed by NO_ if
+// disabled; writing FEATURE or NO_FEATURE enables or disables it.
+const SCHED_FEATURES_FILE: &str = "/sys/kernel/debug/sched/features";
+
+/// A scheduler feature in a given state, written as `HRTICK_DL` (enabled) or
+/// `NO_HRTICK_DL` (disabled).
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq, Eq)]
+pub struct SchedFeature {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl SchedFeature {
+    pub fn new(name: &str, enabled: bool) -> Self {
+        Self { name: name.to_owned(), enabled }
+    }
+}
+
+impl std::str::FromStr for SchedFeature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim_ascii();
+        if s.is_empty() {
+            return Err("Empty scheduler feature".to_owned());
+        }
+
+        match s.strip_prefix("NO_") {
+            Some(name) => Ok(SchedFeature::new(name, false)),
+            None => Ok(SchedFeature::new(s, true)),
+        }
+    }
+}
+
+impl std::fmt::Display for SchedFeature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.enabled {
+            write!(f, "{}", self.name)
+        } else {
+            write!(f, "NO_{}", self.name)
+        }
+    }
+}
+
+/// All the scheduler features with their current state.
+pub fn get_sched_features() -> Result<BTreeMap<String, bool>, Box<dyn std::error::Error>> {
+    let features = std::fs::read_to_string(SCHED_FEATURES_FILE)
+        .map_err(|err| format!("Error in reading {SCHED_FEATURES_FILE}: {err}"))?;
+
+    Ok(features.split_ascii_whitespace()
+        .filter_map(|feature| feature.parse::<SchedFeature>().ok())
+        .map(|feature| (feature.name, feature.enabled))
+        .collect())
+}
+
+pub fn is_sched_feature_enabled(name: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    Ok(*get_sched_features()?.get(name)
+        .ok_or_else(|| format!("Unknown scheduler feature {name}"))?)
+}
+
+pub fn set_sched_feature(name: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if is_sched_feature_enabled(name)? == enabled {
+        return Ok(());
+    }
+
+    let feature = SchedFeature::new(name, enabled);
+    std::fs::write(SCHED_FEATURES_FILE, feature.to_string())
+        .map_err(|err| format!("Error in writing {feature} to {SCHED_FEATURES_FILE}: {err}"))?;
+
+    if is_sched_feature_enabled(name)? != enabled {
+        Err(format!("Scheduler feature {feature} was not applied"))?;
+    }
+
+    __println_debug(|| format!("Set scheduler feature {feature}"));
+
+    Ok(())
+}
+
+/// Changes scheduler features and restores their original state on drop.
+pub struct SchedFeaturesGuard {
+    original: BTreeMap<String, bool>,
+}
+
+impl SchedFeaturesGuard {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self { original: get_sched_features()? })
+    }
+
+    /// Apply the given features. Fails if any of them is unknown, without
+    /// changing anything.
+    pub fn apply(&mut self, features: &[SchedFeature]) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(unknown) = features.iter().find(|feature| !self.original.contains_key(&feature.name)) {
+            Err(format!("Unknown scheduler feature {}", unknown.name))?;
+        }
+
+        features.iter()
+            .try_for_each(|feature| set_sched_feature(&feature.name, feature.enabled))
+    }
+
+    pub fn restore(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.__restore()
+    }
+
+    fn __restore(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let current = get_sched_features()?;
+
+        for (name, enabled) in std::mem::take(&mut self.original) {
+            if current.get(&name).is_some_and(|current| *current != enabled) {
+                set_sched_feature(&name, enabled)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for SchedFeaturesGuard {
+    fn drop(&mut self) {
+        if let Err(err) = self.__restore() {
+            __println_debug(|| format!("Error in restoring scheduler features: {err}"));
+        }
+    }
+}
+
+/// Every combination of the given features being enabled and disabled, e.g.
+/// `[HRTICK_DL]` yields `[[HRTICK_DL], [NO_HRTICK_DL]]`. No features yield a
+/// single empty combination.
+pub fn sched_feature_matrix(names: &[String]) -> Vec<Vec<SchedFeature>> {
+    names.iter()
+        .fold(vec![Vec::new()], |combinations, name| {
+            combinations.into_iter()
+                .flat_map(|combination| [true, false].map(|enabled| {
+                    let mut combination = combination.clone();
+                    combination.push(SchedFeature::new(name, enabled));
+                    combination
+                }))
+                .collect()
+        })
+}
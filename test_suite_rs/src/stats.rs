@@ -0,0 +1,427 @@
+use std::time::{Duration, Instant};
+
+use crate::{process::get_process_runtime_ns, utils::{ExitFlag, create_ctrlc_handler}};
+
+pub mod prelude {
+    pub use super::{
+        StatArgs,
+        Samples,
+        StatOutcome,
+        StatReport,
+        BandwidthMeter,
+        collect_window_samples,
+        collect_window_samples_multi,
+        check_samples,
+        Histogram,
+    };
+}
+
+#[derive(clap::Parser, Debug, Clone)]
+pub struct StatArgs {
+    /// number of measurement windows the test run is split into
+    #[arg(short = 'k', long = "trials", value_name = "u64", default_value = "10")]
+    pub trials: u64,
+
+    /// maximum accepted error, as an absolute amount of bandwidth (1.0 is a
+    /// whole cpu), not relative to the expected one. The defaults changed
+    /// from 2.5% of the expected bandwidth to 0.01 (per cpu in the tests
+    /// measuring several cpus) [default: test specific]
+    #[arg(long = "tolerance", value_name = "f64")]
+    pub tolerance: Option<f64>,
+
+    /// confidence level of the interval on the mean (0.90, 0.95 or 0.99)
+    #[arg(long = "confidence", value_name = "f64", default_value = "0.95")]
+    pub confidence: f64,
+}
+
+impl StatArgs {
+    /// Split the test's running time into the requested number of windows.
+    /// Without a maximum running time, windows last one second each and are
+    /// collected until Ctrl+C is received.
+    pub fn window_secs(&self, max_time: Option<u64>) -> f32 {
+        match max_time {
+            Some(max_time) => max_time as f32 / self.trials.max(1) as f32,
+            None => 1f32,
+        }
+    }
+
+    pub fn num_windows(&self, max_time: Option<u64>) -> Option<u64> {
+        max_time.map(|_| self.trials.max(1))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Samples {
+    samples: Vec<f64>,
+}
+
+impl Samples {
+    pub fn new(samples: Vec<f64>) -> Self {
+        Self { samples }
+    }
+
+    pub fn raw(&self) -> &[f64] {
+        &self.samples
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// Sample (Bessel corrected) standard deviation.
+    pub fn std_dev(&self) -> f64 {
+        if self.samples.len() < 2 {
+            return 0f64;
+        }
+
+        let mean = self.mean();
+        let var = self.samples.iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>() / (self.samples.len() - 1) as f64;
+
+        var.sqrt()
+    }
+
+    /// Two-sided Student's t confidence interval on the mean.
+    pub fn confidence_interval(&self, confidence: f64) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+        if self.samples.is_empty() {
+            return Err("Cannot compute a confidence interval without samples".into());
+        }
+
+        // a single sample says nothing about the variance: the mean could be
+        // anywhere
+        let mean = self.mean();
+        if self.samples.len() < 2 {
+            return Ok((f64::NEG_INFINITY, f64::INFINITY));
+        }
+
+        let t = student_t_quantile(confidence, self.samples.len() as u64 - 1)?;
+        let half_width = t * self.std_dev() / (self.samples.len() as f64).sqrt();
+
+        Ok((mean - half_width, mean + half_width))
+    }
+}
+
+impl std::fmt::Display for Samples {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[")?;
+
+        let mut iter = self.samples.iter();
+        if let Some(sample) = iter.next() {
+            write!(f, "{sample:.5}")?;
+
+            for sample in iter {
+                write!(f, ", {sample:.5}")?;
+            }
+        }
+
+        write!(f, "]")
+    }
+}
+
+// Two-sided critical values of Student's t distribution, for 1 to 30 degrees
+// of freedom. Larger sample sizes use the normal approximation.
+const T_TABLE_90: [f64; 30] = [
+    6.314, 2.920, 2.353, 2.132, 2.015, 1.943, 1.895, 1.860, 1.833, 1.812,
+    1.796, 1.782, 1.771, 1.761, 1.753, 1.746, 1.740, 1.734, 1.729, 1.725,
+    1.721, 1.717, 1.714, 1.711, 1.708, 1.706, 1.703, 1.701, 1.699, 1.697,
+];
+
+const T_TABLE_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+const T_TABLE_99: [f64; 30] = [
+    63.657, 9.925, 5.841, 4.604, 4.032, 3.707, 3.499, 3.355, 3.250, 3.169,
+    3.106, 3.055, 3.012, 2.977, 2.947, 2.921, 2.898, 2.878, 2.861, 2.845,
+    2.831, 2.819, 2.807, 2.797, 2.787, 2.779, 2.771, 2.763, 2.756, 2.750,
+];
+
+fn student_t_quantile(confidence: f64, degrees: u64) -> Result<f64, Box<dyn std::error::Error>> {
+    let (table, normal) =
+        if (confidence - 0.90).abs() < 1e-9 {
+            (&T_TABLE_90, 1.645)
+        } else if (confidence - 0.95).abs() < 1e-9 {
+            (&T_TABLE_95, 1.960)
+        } else if (confidence - 0.99).abs() < 1e-9 {
+            (&T_TABLE_99, 2.576)
+        } else {
+            return Err(format!("Unsupported confidence level {confidence}, use one of 0.90, 0.95, 0.99"))?;
+        };
+
+    Ok(table.get(degrees as usize - 1).copied().unwrap_or(normal))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatOutcome {
+    Pass,
+    Fail,
+    Inconclusive,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatReport {
+    pub what: String,
+    pub outcome: StatOutcome,
+    pub expected: f64,
+    pub tolerance: f64,
+    pub mean: f64,
+    pub interval: (f64, f64),
+    pub samples: Samples,
+}
+
+impl std::fmt::Display for StatReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} used {:.5} units (CI [{:.5}, {:.5}]), expected {:.5} ± {:.5}; samples: {}",
+            self.what, self.mean, self.interval.0, self.interval.1,
+            self.expected, self.tolerance, self.samples)
+    }
+}
+
+/// Compare the confidence interval of the samples' mean against the accepted
+/// range `expected ± tolerance`. The check passes if the whole interval lies
+/// inside the range, fails if it lies completely outside of it, and is
+/// inconclusive otherwise, always with fewer than two samples.
+pub fn check_samples(what: &str, samples: Samples, expected: f64, tolerance: f64, confidence: f64) -> Result<StatReport, Box<dyn std::error::Error>> {
+    let (lo, hi) = samples.confidence_interval(confidence)?;
+    let (min, max) = (expected - tolerance, expected + tolerance);
+
+    let outcome =
+        if lo >= min && hi <= max {
+            StatOutcome::Pass
+        } else if hi < min || lo > max {
+            StatOutcome::Fail
+        } else {
+            StatOutcome::Inconclusive
+        };
+
+    Ok(StatReport {
+        what: what.to_owned(),
+        outcome,
+        expected,
+        tolerance,
+        mean: samples.mean(),
+        interval: (lo, hi),
+        samples,
+    })
+}
+
+/// Measures the CPU bandwidth consumed by a set of processes between
+/// consecutive calls of [`BandwidthMeter::sample`], using the scheduler's
+/// nanosecond runtime counters.
+pub struct BandwidthMeter {
+    pids: Vec<u32>,
+    last_runtime_ns: u64,
+    last_time: Instant,
+}
+
+impl BandwidthMeter {
+    pub fn new(pids: &[u32]) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut meter = Self {
+            pids: pids.to_owned(),
+            last_runtime_ns: 0,
+            last_time: Instant::now(),
+        };
+
+        meter.last_runtime_ns = meter.total_runtime_ns()?;
+        Ok(meter)
+    }
+
+    fn total_runtime_ns(&self) -> Result<u64, Box<dyn std::error::Error>> {
+        Ok(self.pids.iter()
+            .map(|pid| get_process_runtime_ns(*pid))
+            .sum::<Result<u64, _>>()?)
+    }
+
+    /// Runtime (in seconds) consumed since the last sample, and the elapsed
+    /// wall-clock time.
+    pub fn sample_runtime(&mut self) -> Result<(f64, f64), Box<dyn std::error::Error>> {
+        let now = Instant::now();
+        let runtime_ns = self.total_runtime_ns()?;
+
+        let used = runtime_ns.saturating_sub(self.last_runtime_ns) as f64 / 1e9;
+        let elapsed = now.duration_since(self.last_time).as_secs_f64();
+
+        self.last_runtime_ns = runtime_ns;
+        self.last_time = now;
+
+        Ok((used, elapsed))
+    }
+
+    /// Bandwidth (in CPU units) consumed since the last sample.
+    pub fn sample(&mut self) -> Result<f64, Box<dyn std::error::Error>> {
+        let (used, elapsed) = self.sample_runtime()?;
+        Ok(used / elapsed)
+    }
+}
+
+/// Call `fun` at the end of each of `num_windows` measurement windows of
+/// `window_secs` seconds, collecting the returned values. If `num_windows` is
+/// `None`, windows are collected until Ctrl+C is received.
+pub fn collect_window_samples<F>(window_secs: f32, num_windows: Option<u64>, ctrlc_flag: Option<ExitFlag>, mut fun: F) -> Result<Samples, Box<dyn std::error::Error>>
+    where F: FnMut() -> Result<f64, Box<dyn std::error::Error>>
+{
+    let mut samples = collect_window_samples_multi(window_secs, num_windows, ctrlc_flag, || Ok(vec![fun()?]))?;
+
+    Ok(samples.remove(0))
+}
+
+/// Like [`collect_window_samples`], for several quantities measured over the
+/// same windows (e.g. one per CPU). The function must always return the same
+/// number of values.
+pub fn collect_window_samples_multi<F>(window_secs: f32, num_windows: Option<u64>, ctrlc_flag: Option<ExitFlag>, mut fun: F) -> Result<Vec<Samples>, Box<dyn std::error::Error>>
+    where F: FnMut() -> Result<Vec<f64>, Box<dyn std::error::Error>>
+{
+    let exit = match ctrlc_flag {
+        Some(exit) => exit,
+        None => create_ctrlc_handler()?,
+    };
+
+    let periodic_ch = crossbeam::channel::tick(Duration::from_secs_f32(window_secs));
+
+    let mut samples: Vec<Vec<f64>> = Vec::new();
+    let mut num_samples = 0u64;
+    while num_windows.is_none_or(|num_windows| num_samples < num_windows) {
+        if exit.is_exit() {
+            break;
+        }
+
+        periodic_ch.recv()?;
+        let values = fun()?;
+        if samples.is_empty() {
+            samples = vec![Vec::new(); values.len()];
+        } else if values.len() != samples.len() {
+            return Err(format!("Expected {} values per window, got {}", samples.len(), values.len()).into());
+        }
+
+        samples.iter_mut().zip(values)
+            .for_each(|(samples, value)| samples.push(value));
+        num_samples += 1;
+    }
+
+    if num_samples == 0 || samples.is_empty() {
+        return Err("No measurement window completed".into());
+    }
+
+    Ok(samples.into_iter().map(Samples::new).collect())
+}
+
+/// Latency histogram with fixed-width buckets, in microseconds. Values past
+/// the last bucket are counted as overflows, but still contribute to the
+/// minimum, maximum and mean.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bucket_width_us: u64,
+    buckets: Vec<u64>,
+    overflows: u64,
+    count: u64,
+    sum_us: u64,
+    min_us: u64,
+    max_us: u64,
+}
+
+impl Histogram {
+    pub fn new(bucket_width_us: u64, num_buckets: usize) -> Self {
+        Self {
+            bucket_width_us: bucket_width_us.max(1),
+            buckets: vec![0; num_buckets],
+            overflows: 0,
+            count: 0,
+            sum_us: 0,
+            min_us: u64::MAX,
+            max_us: 0,
+        }
+    }
+
+    pub fn add(&mut self, value_us: u64) {
+        match self.buckets.get_mut((value_us / self.bucket_width_us) as usize) {
+            Some(bucket) => *bucket += 1,
+            None => self.overflows += 1,
+        }
+
+        self.count += 1;
+        self.sum_us += value_us;
+        self.min_us = self.min_us.min(value_us);
+        self.max_us = self.max_us.max(value_us);
+    }
+
+    pub fn merge(&mut self, other: &Histogram) {
+        if self.bucket_width_us != other.bucket_width_us {
+            panic!("Cannot merge histograms with different bucket widths");
+        }
+
+        if self.buckets.len() < other.buckets.len() {
+            self.buckets.resize(other.buckets.len(), 0);
+        }
+
+        self.buckets.iter_mut().zip(other.buckets.iter())
+            .for_each(|(bucket, other)| *bucket += other);
+
+        self.overflows += other.overflows;
+        self.count += other.count;
+        self.sum_us += other.sum_us;
+        self.min_us = self.min_us.min(other.min_us);
+        self.max_us = self.max_us.max(other.max_us);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min_us(&self) -> u64 {
+        if self.count == 0 { 0 } else { self.min_us }
+    }
+
+    pub fn max_us(&self) -> u64 {
+        self.max_us
+    }
+
+    pub fn mean_us(&self) -> f64 {
+        self.sum_us as f64 / self.count as f64
+    }
+
+    pub fn overflows(&self) -> u64 {
+        self.overflows
+    }
+
+    /// Upper bound of the bucket containing the given percentile (0 - 100).
+    /// Percentiles falling in the overflows are reported as the maximum.
+    pub fn percentile_us(&self, percentile: f64) -> u64 {
+        let target = (self.count as f64 * percentile / 100f64).ceil() as u64;
+
+        let mut seen = 0u64;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket;
+            if seen >= target && seen > 0 {
+                return ((i as u64 + 1) * self.bucket_width_us).min(self.max_us);
+            }
+        }
+
+        self.max_us
+    }
+
+    /// Non-empty buckets, as (lower bound in us, count).
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.buckets.iter().enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(i, count)| (i as u64 * self.bucket_width_us, *count))
+    }
+}
+
+impl std::fmt::Display for Histogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "min {} us, avg {:.1} us, p50 {} us, p99 {} us, max {} us ({} samples, {} overflows)",
+            self.min_us(), self.mean_us(), self.percentile_us(50f64), self.percentile_us(99f64),
+            self.max_us(), self.count, self.overflows)
+    }
+}
@@ -51,6 +51,11 @@ pub struct MyArgs {
     /// number of instances per job
     #[arg(short = 'j', long = "job", value_name = "u64", default_value = "200")]
     pub num_instances_per_job: u64,
+
+    /// how to choose the CPUs of each run: first, one-per-core, same-package,
+    /// numa:<node>
+    #[arg(long = "placement", value_name = "policy", default_value = "first")]
+    pub placement: CpuPlacement,
 }
 
 pub struct MyResult {
@@ -106,6 +111,17 @@ pub struct TasksetRunResult {
     results: Vec<TasksetRunResultInstance>,
 }
 
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct TasksetOutputSummary {
+    pub taskset: String,
+    pub config: String,
+    pub num_jobs: u64,
+    pub num_overruns: u64,
+    pub overruns_ratio: f64,
+    pub worst_overrun: f64,
+}
+
 #[derive(Clone)]
 struct TasksetRunResultInsights {
     num_overruns: u64,
@@ -162,7 +178,7 @@ fn run_taskset(run: TasksetRun, args: &MyArgs, cycles: Option<u64>)
 
     migrate_task_to_cgroup(&args.cgroup, std::process::id())?;
     set_scheduler(std::process::id(), SchedPolicy::RR(99))?;
-    set_cpuset_to_pid(std::process::id(), &CpuSet::any_subset(run.config.num_cpus)?)?;
+    set_cpuset_to_pid(std::process::id(), &args.placement.cpu_set(run.config.num_cpus)?)?;
 
     let pthread_data = PeriodicThreadData {
         start_priority: 98,
@@ -221,8 +237,12 @@ fn compute_insights(run: &TasksetRun, args: &MyArgs) -> TasksetRunInsights {
 }
 
 fn compute_result_insights(run: &TasksetRunResult) -> TasksetRunResultInsights {
+    compute_instances_insights(&run.results)
+}
+
+fn compute_instances_insights(results: &[TasksetRunResultInstance]) -> TasksetRunResultInsights {
     let (num_overruns, worst_overrun) = 
-        run.results.iter()
+        results.iter()
         .fold((0u64, f64::NEG_INFINITY), |(mut num_overruns, worst_overrun), job_instance| {
             if job_instance.deadline_offset > 0f64 { num_overruns+= 1; }
             (num_overruns, worst_overrun.max(job_instance.deadline_offset))
@@ -230,7 +250,7 @@ fn compute_result_insights(run: &TasksetRunResult) -> TasksetRunResultInsights {
 
     TasksetRunResultInsights {
         num_overruns,
-        overruns_ratio: num_overruns as f64 / run.results.len() as f64,
+        overruns_ratio: num_overruns as f64 / results.len() as f64,
         worst_overrun,
     }
 }
@@ -540,4 +560,54 @@ pub fn main_read_results_array(args: MyArgsAll) -> Result<MyResult, Box<dyn std:
         failures, total_runs, failures as f64 / total_runs as f64);
 
     Ok(MyResult { results })
+}
+
+/// Summarize all the taskset outputs found in a results/output directory, as
+/// written by the taskset runner (`<output_dir>/<taskset>/output-<config>`).
+pub fn read_output_dir(output_dir: &str) -> Result<Vec<TasksetOutputSummary>, Box<dyn std::error::Error>> {
+    let mut summaries = Vec::new();
+    for taskset_dir in std::fs::read_dir(output_dir)
+        .map_err(|err| format!("Output directory {} error: {}", output_dir, err))?
+    {
+        let taskset_dir = taskset_dir?.path();
+        if !taskset_dir.is_dir() {
+            continue;
+        }
+
+        let taskset = __os_str_to_str(taskset_dir.file_name()
+            .ok_or("Unknown directory")?)?;
+
+        for output_file in std::fs::read_dir(&taskset_dir)
+            .map_err(|err| format!("Taskset output directory {:?} error: {}", taskset_dir, err))?
+        {
+            let output_file = output_file?.path();
+            let file_name = __os_str_to_str(output_file.file_name()
+                .ok_or("File name not found")?)?;
+
+            let Some(config) = file_name.strip_prefix("output-") else { continue; };
+            if !output_file.is_file() {
+                continue;
+            }
+
+            let results = parse_taskset_results(&__path_to_str(&output_file)?)?;
+            if results.is_empty() {
+                Err(format!("Taskset output file {:?} has no jobs", output_file))?;
+            }
+
+            let insights = compute_instances_insights(&results);
+
+            summaries.push(TasksetOutputSummary {
+                taskset: taskset.clone(),
+                config: config.to_owned(),
+                num_jobs: results.len() as u64,
+                num_overruns: insights.num_overruns,
+                overruns_ratio: insights.overruns_ratio,
+                worst_overrun: insights.worst_overrun,
+            });
+        }
+    }
+
+    summaries.sort_unstable_by(|l, r| (&l.taskset, &l.config).cmp(&(&r.taskset, &r.config)));
+
+    Ok(summaries)
 }
\ No newline at end of file
@@ -1,5 +1,7 @@
 use std::{io::Write, time::Duration};
 
+use crate::report::{TestOutcome, report_start, report_expected_metric, report_outcome};
+
 pub mod prelude {
     pub use super::{
         __shell,
@@ -20,7 +22,8 @@ pub mod prelude {
         batch_test_success_details,
         batch_test_skipped,
         batch_test_failure,
-        get_fair_server_avg_bw,
+        batch_test_inconclusive,
+        batch_test_result_stat,
         Skippable,
     };
 }
@@ -50,6 +53,7 @@ pub fn is_batch_test() -> bool {
     is_env_var_set("BATCH_TEST")
 }
 
+/// Set on Ctrl+C, and stays set: every clone sees it, also after a check.
 #[derive(Clone)]
 pub struct ExitFlag {
     ch: crossbeam::channel::Receiver<()>,
@@ -60,17 +64,19 @@ impl ExitFlag {
         use crossbeam::channel::TryRecvError::*;
 
         match self.ch.try_recv() {
-            Ok(()) => true,
             Err(Empty) => false,
-            _ => panic!("unexpected"),
+            Ok(()) | Err(Disconnected) => true,
         }
     }
 }
 
 pub fn create_ctrlc_handler() -> Result<ExitFlag, Box<dyn std::error::Error>> {
-    let (send, recv) = crossbeam::channel::bounded(1);
+    let (send, recv) = crossbeam::channel::bounded::<()>(1);
+    let send = std::sync::Mutex::new(Some(send));
 
-    ctrlc::set_handler(move || { send.send(()).unwrap(); })?;
+    // dropping the sender disconnects the channel, which wakes up every
+    // receiver now and in the future
+    ctrlc::set_handler(move || { send.lock().unwrap().take(); })?;
     Ok(ExitFlag { ch: recv })
 }
 
@@ -148,7 +154,7 @@ pub fn mount_debug_fs() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-pub fn batch_test_success() {
+fn print_success() {
     if is_env_var_set("TERM_COLORS") {
         println!("\x1b[32mSuccess ✔\x1b[0m");
     } else {
@@ -156,14 +162,21 @@ pub fn batch_test_success() {
     }
 }
 
+pub fn batch_test_success() {
+    print_success();
+    report_outcome(TestOutcome::Success, None);
+}
+
 pub fn batch_test_success_details<T: std::fmt::Display>(msg: T) {
-    batch_test_success();
+    print_success();
     
     if is_env_var_set("TERM_COLORS") {
         println!("\x1b[32m    Details: \x1b[0m{msg}");
     } else {
         println!("    Details: {msg}");
     }
+
+    report_outcome(TestOutcome::Success, Some(msg.to_string()));
 }
 
 pub fn batch_test_failure<E: std::fmt::Display>(err: E) {
@@ -172,6 +185,8 @@ pub fn batch_test_failure<E: std::fmt::Display>(err: E) {
     } else {
         println!("Failure ✖\n    Reason: {err}")
     }
+
+    report_outcome(TestOutcome::Failure, Some(err.to_string()));
 }
 
 pub fn batch_test_skipped<T: std::fmt::Display>(msg: T) {
@@ -180,12 +195,30 @@ pub fn batch_test_skipped<T: std::fmt::Display>(msg: T) {
     } else {
         println!("Skipped ⛒\n    Reason: {msg}");
     }
+
+    report_outcome(TestOutcome::Skipped, Some(msg.to_string()));
+}
+
+pub fn batch_test_inconclusive<T: std::fmt::Display>(msg: T) {
+    if is_env_var_set("TERM_COLORS") {
+        println!("\x1b[33mInconclusive ?\n    Reason: \x1b[0m{msg}");
+    } else {
+        println!("Inconclusive ?\n    Reason: {msg}");
+    }
+
+    report_outcome(TestOutcome::Inconclusive, Some(msg.to_string()));
 }
 
 pub fn batch_test_header(test_name: &str, test_category: &str) {
     match std::env::var("BATCH_TEST_CUSTOM_NAME") {
-        Ok(custom) if custom != "" => print!("[{}] {}: ", test_category, custom),
-        _ => print!("[{}] {}: ", test_category, test_name),
+        Ok(custom) if custom != "" => {
+            print!("[{}] {}: ", test_category, custom);
+            report_start(test_category, &custom);
+        },
+        _ => {
+            print!("[{}] {}: ", test_category, test_name);
+            report_start(test_category, test_name);
+        },
     };
 
     std::io::stdout().flush().unwrap();
@@ -245,34 +278,46 @@ pub fn batch_test_result_skippable_details<T: std::fmt::Display>(result: Result<
     }
 }
 
-pub fn get_fair_server_avg_bw() -> Result<f64, Box<dyn std::error::Error>> {
-    let mut avg_bw = 0f64;
-    let mut num_cpus = 0f64;
-
-    for entry in std::fs::read_dir("/sys/kernel/debug/sched/fair_server")? {
-        let entry = entry?.path();
-        if entry.is_dir() {
-            let entry = entry.into_os_string().into_string().unwrap();
-
-            let runtime: u64 =
-                std::fs::read_to_string(format!("{entry}/runtime"))
-                    .map_err(|err| format!("Error in reading {entry}/runtime: {err}"))
-                    .and_then(|value| value.trim().parse::<u64>()
-                        .map_err(|err| format!("Error in parsing {entry}/runtime: {err}"))
-                    )?;
-            let period: u64 = 
-                std::fs::read_to_string(format!("{entry}/period"))
-                    .map_err(|err| format!("Error in reading {entry}/period: {err}"))
-                    .and_then(|value| value.trim().parse::<u64>()
-                        .map_err(|err| format!("Error in parsing {entry}/period: {err}"))
-                    )?;
-
-            avg_bw += runtime as f64 / period as f64;
-            num_cpus += 1f64;
-        }
+pub fn batch_test_result_stat(result: Result<Vec<crate::stats::StatReport>, Box<dyn std::error::Error>>) -> Result<(), Box<dyn std::error::Error>> {
+    use crate::stats::StatOutcome;
+
+    let result = result.map(|reports| {
+        reports.iter()
+            .for_each(|report| report_expected_metric(&report.what, report.mean, report.expected));
+
+        let outcome =
+            if reports.iter().any(|report| report.outcome == StatOutcome::Fail) {
+                StatOutcome::Fail
+            } else if reports.iter().any(|report| report.outcome == StatOutcome::Inconclusive) {
+                StatOutcome::Inconclusive
+            } else {
+                StatOutcome::Pass
+            };
+
+        let msg = reports.iter()
+            .map(|report| report.to_string())
+            .collect::<Vec<_>>()
+            .join("\n             ");
+
+        (outcome, msg)
+    });
+
+    let result = match result {
+        Ok((StatOutcome::Pass, msg)) => { batch_test_success_details(&msg); Ok(()) },
+        // unless accepted, an inconclusive check may hide a regression
+        Ok((StatOutcome::Inconclusive, msg)) => {
+            batch_test_inconclusive(&msg);
+            if is_env_var_set("ALLOW_INCONCLUSIVE") { Ok(()) } else { Err(msg.into()) }
+        },
+        Ok((StatOutcome::Fail, msg)) => { batch_test_failure(&msg); Err(msg.into()) },
+        Err(err) => { batch_test_failure(&err); Err(err) },
+    };
+
+    if is_batch_test() {
+        Ok(())
+    } else {
+        result
     }
-    
-    Ok(avg_bw / num_cpus)
 }
 
 pub enum Skippable<T, E> {